@@ -1,12 +1,21 @@
-use crate::ai::{self, GenerateCommitMessage, Model, SYSTEM_PROMPT, build_user_prompt};
+use crate::ai::{
+    self, ClientOptions, ExtraFields, GenerateCommitMessage, HttpOptions, Model, Prompt, TokenSink,
+    Usage,
+};
 use serde::{Deserialize, Serialize};
 
+// Anthropic requires max_tokens, so "uncapped" maps to a ceiling every model supports
+const UNCAPPED_MAX_TOKENS: u32 = 8192;
+
+const RESERVED_FIELDS: &[&str] = &["model", "system", "messages"];
+
 #[derive(Serialize)]
 struct ClaudeRequest {
     model: String,
     max_tokens: u32,
     system: String,
     messages: Vec<Message>,
+    stop_sequences: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -18,6 +27,7 @@ struct Message {
 #[derive(Deserialize)]
 struct ClaudeResponse {
     content: Vec<Content>,
+    usage: Option<Usage>,
 }
 
 #[allow(dead_code)]
@@ -40,48 +50,115 @@ struct ErrorDetail {
     message: String,
 }
 
+#[derive(Deserialize)]
+struct StreamEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    delta: Option<StreamDelta>,
+    error: Option<StreamError>,
+    message: Option<StreamMessage>,
+    usage: Option<StreamUsage>,
+}
+
+#[derive(Deserialize)]
+struct StreamMessage {
+    usage: Option<StreamUsage>,
+}
+
+// message_start carries the input tokens, message_delta the running output count
+#[derive(Deserialize)]
+struct StreamUsage {
+    input_tokens: Option<u32>,
+    output_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamError {
+    message: String,
+}
+
+// Text arrives in content_block_delta events, overload errors can show up mid-stream
+fn read_stream_event(data: &str, usage: &mut Option<Usage>) -> anyhow::Result<Option<String>> {
+    let event: StreamEvent = serde_json::from_str(data)?;
+    let event_usage = event
+        .usage
+        .or_else(|| event.message.and_then(|message| message.usage));
+    if let Some(event_usage) = event_usage {
+        let usage = usage.get_or_insert_default();
+        usage.input_tokens = event_usage.input_tokens.unwrap_or(usage.input_tokens);
+        usage.output_tokens = event_usage.output_tokens.unwrap_or(usage.output_tokens);
+    }
+
+    match event.event_type.as_str() {
+        "content_block_delta" => Ok(event.delta.and_then(|delta| delta.text)),
+        "error" => Err(anyhow::anyhow!(
+            "Claude API error: {}",
+            event
+                .error
+                .map_or_else(|| "unknown stream error".to_string(), |error| error.message)
+        )),
+        _ => Ok(None),
+    }
+}
+
 pub struct Client {
     api_key: String,
     model: ai::Model,
+    max_tokens: u32,
+    extra_fields: ExtraFields,
+    http: HttpOptions,
+    http_client: reqwest::Client,
+    log_request: bool,
 }
 
 impl Client {
-    pub fn new(api_key: String, model: Model) -> Self {
+    pub fn new(api_key: String, model: Model, options: &ClientOptions) -> Self {
         assert!(model.is_claude(), "Model must be a Claude model");
 
-        Self { api_key, model }
+        Self {
+            api_key,
+            model,
+            max_tokens: options.resolved_max_tokens().unwrap_or(UNCAPPED_MAX_TOKENS),
+            extra_fields: options.extra_fields.clone(),
+            http: options.http,
+            http_client: ai::http_client(options.http),
+            log_request: options.log_request,
+        }
     }
-}
 
-#[async_trait::async_trait]
-impl GenerateCommitMessage for Client {
-    async fn generate_commit_message(
-        &self,
-        files: &[String],
-        diff: &str,
-        context: Option<&str>,
-    ) -> anyhow::Result<String> {
-        let http_client = reqwest::Client::new();
-        let prompt = build_user_prompt(files, diff, context);
-
-        let request = ClaudeRequest {
+    fn build_request(&self, prompt: &Prompt) -> ClaudeRequest {
+        ClaudeRequest {
             model: self.model.to_api_str(),
-            max_tokens: 1024,
-            system: SYSTEM_PROMPT.to_string(),
+            max_tokens: self.max_tokens,
+            stop_sequences: ai::stop_sequences(),
+            system: prompt.system.clone(),
             messages: vec![Message {
                 role: "user".to_string(),
-                content: prompt,
+                content: prompt.user.clone(),
             }],
-        };
+        }
+    }
 
-        let response = http_client
+    async fn send(&self, request: &serde_json::Value) -> anyhow::Result<reqwest::Response> {
+        ai::ensure_online(self.http)?;
+        if self.log_request {
+            ai::log_request(self.model.provider(), request, Some(&self.api_key));
+        }
+        let response = self
+            .http_client
             .post("https://api.anthropic.com/v1/messages")
             .header("content-type", "application/json")
             .header("anthropic-version", "2023-06-01")
             .header("x-api-key", &self.api_key)
-            .json(&request)
+            .json(request)
             .send()
-            .await?;
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -95,13 +172,218 @@ impl GenerateCommitMessage for Client {
                 return Err(anyhow::anyhow!("HTTP error {}: {}", status, error_text));
             }
         }
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl GenerateCommitMessage for Client {
+    async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+        Ok(self.generate_with_usage(prompt).await?.0)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &Prompt,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        let request = ai::request_body(
+            &self.build_request(prompt),
+            &self.extra_fields,
+            RESERVED_FIELDS,
+        )?;
+        let response = self.send(&request).await?;
 
-        let claude_response: ClaudeResponse = response.json().await?;
+        let claude_response: ClaudeResponse = response
+            .json()
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
 
         if let Some(content) = claude_response.content.first() {
-            Ok(content.text.trim().to_string())
+            Ok((content.text.trim().to_string(), claude_response.usage))
         } else {
             Err(anyhow::anyhow!("No response from Claude"))
         }
     }
+
+    async fn generate_streaming(
+        &self,
+        prompt: &Prompt,
+        on_token: &TokenSink<'_>,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        let mut request = ai::request_body(
+            &self.build_request(prompt),
+            &self.extra_fields,
+            RESERVED_FIELDS,
+        )?;
+        request["stream"] = serde_json::Value::Bool(true);
+        let response = self.send(&request).await?;
+
+        let mut message = String::new();
+        let mut usage = None;
+        ai::read_sse(response, self.model.provider(), self.http, |data| {
+            if let Some(token) = read_stream_event(data, &mut usage)? {
+                on_token(&token);
+                message.push_str(&token);
+            }
+            Ok(())
+        })
+        .await?;
+
+        match message.trim() {
+            "" => Err(anyhow::anyhow!("No response from Claude")),
+            message => Ok((message.to_string(), usage)),
+        }
+    }
+}
+
+pub async fn list_models(api_key: &str, http: HttpOptions) -> anyhow::Result<Vec<String>> {
+    ai::ensure_online(http)?;
+    let response = ai::http_client(http)
+        .get("https://api.anthropic.com/v1/models")
+        .header("anthropic-version", "2023-06-01")
+        .header("x-api-key", api_key)
+        .send()
+        .await?;
+
+    ai::parse_model_list(&ai::model_list_body(response).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::build_system_prompt;
+
+    fn test_prompt() -> Prompt {
+        Prompt {
+            system: build_system_prompt(&["always mention the ticket number"]),
+            user: "staged changes".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_request_carries_system_and_user_prompt() {
+        let client = Client::new(
+            "test-key".to_string(),
+            Model::Sonnet4,
+            &ClientOptions::default(),
+        );
+
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+
+        assert!(
+            body["system"]
+                .as_str()
+                .unwrap()
+                .contains("always mention the ticket number")
+        );
+        assert_eq!(body["messages"][0]["content"], "staged changes");
+    }
+
+    #[test]
+    fn test_request_max_tokens_follows_options() {
+        let options = ClientOptions {
+            body: true,
+            ..Default::default()
+        };
+        let client = Client::new("test-key".to_string(), Model::Sonnet4, &options);
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+        assert_eq!(body["max_tokens"], ai::BODY_MAX_TOKENS);
+
+        let options = ClientOptions {
+            max_tokens: Some(0),
+            ..Default::default()
+        };
+        let client = Client::new("test-key".to_string(), Model::Sonnet4, &options);
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+        assert_eq!(body["max_tokens"], UNCAPPED_MAX_TOKENS);
+    }
+
+    #[test]
+    fn test_response_usage_is_parsed() {
+        let response: ClaudeResponse = serde_json::from_str(
+            r#"{
+                "content": [{"type": "text", "text": "feat: add usage"}],
+                "usage": {"input_tokens": 812, "output_tokens": 14, "cache_read_input_tokens": 0}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                input_tokens: 812,
+                output_tokens: 14
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_stream_event() {
+        let mut usage = None;
+        assert_eq!(
+            read_stream_event(
+                r#"{"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "fix"}}"#,
+                &mut usage
+            )
+            .unwrap(),
+            Some("fix".to_string())
+        );
+        assert_eq!(
+            read_stream_event(
+                r#"{"type": "message_start", "message": {"id": "msg_1"}}"#,
+                &mut usage
+            )
+            .unwrap(),
+            None
+        );
+        assert_eq!(usage, None);
+        assert_eq!(
+            read_stream_event(
+                r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#,
+                &mut usage
+            )
+            .unwrap_err()
+            .to_string(),
+            "Claude API error: Overloaded"
+        );
+    }
+
+    #[test]
+    fn test_stream_usage_combines_start_and_delta() {
+        let mut usage = None;
+        read_stream_event(
+            r#"{"type": "message_start", "message": {"id": "msg_1", "usage": {"input_tokens": 812, "output_tokens": 1}}}"#,
+            &mut usage,
+        )
+        .unwrap();
+        read_stream_event(
+            r#"{"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 14}}"#,
+            &mut usage,
+        )
+        .unwrap();
+
+        assert_eq!(
+            usage,
+            Some(Usage {
+                input_tokens: 812,
+                output_tokens: 14
+            })
+        );
+    }
+
+    #[test]
+    fn test_request_serializes_stop_sequences() {
+        let client = Client::new(
+            "test-key".to_string(),
+            Model::Sonnet4,
+            &ClientOptions::default(),
+        );
+
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+
+        assert_eq!(
+            body["stop_sequences"],
+            serde_json::json!(ai::STOP_SEQUENCES)
+        );
+    }
 }
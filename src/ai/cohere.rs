@@ -0,0 +1,293 @@
+use crate::ai::{
+    self, ClientOptions, ExtraFields, GenerateCommitMessage, HttpOptions, Model, Prompt, Usage,
+};
+use serde::{Deserialize, Serialize};
+
+const RESERVED_FIELDS: &[&str] = &["model", "messages"];
+
+#[derive(Serialize)]
+struct CohereRequest {
+    model: String,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    temperature: f32,
+    stop_sequences: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct CohereResponse {
+    message: ResponseMessage,
+    usage: Option<CohereUsage>,
+}
+
+#[derive(Deserialize)]
+struct CohereUsage {
+    billed_units: Option<TokenCounts>,
+    tokens: Option<TokenCounts>,
+}
+
+#[derive(Deserialize)]
+struct TokenCounts {
+    #[serde(default)]
+    input_tokens: f64,
+    #[serde(default)]
+    output_tokens: f64,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: Vec<Content>,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    #[serde(rename = "type")]
+    content_type: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct CohereModelList {
+    models: Vec<CohereModel>,
+}
+
+#[derive(Deserialize)]
+struct CohereModel {
+    name: String,
+    #[serde(default)]
+    endpoints: Vec<String>,
+}
+
+pub struct Client {
+    api_key: String,
+    model: ai::Model,
+    max_tokens: Option<u32>,
+    extra_fields: ExtraFields,
+    http: HttpOptions,
+    http_client: reqwest::Client,
+    log_request: bool,
+}
+
+impl Client {
+    pub fn new(api_key: String, model: Model, options: &ClientOptions) -> Self {
+        assert!(model.is_cohere(), "Model must be a Cohere model");
+
+        Self {
+            api_key,
+            model,
+            max_tokens: options.resolved_max_tokens(),
+            extra_fields: options.extra_fields.clone(),
+            http: options.http,
+            http_client: ai::http_client(options.http),
+            log_request: options.log_request,
+        }
+    }
+
+    fn build_request(&self, prompt: &Prompt) -> CohereRequest {
+        CohereRequest {
+            model: self.model.to_api_str(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: prompt.system.clone(),
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: prompt.user.clone(),
+                },
+            ],
+            max_tokens: self.max_tokens,
+            stop_sequences: ai::stop_sequences(),
+            temperature: 0.3,
+        }
+    }
+}
+
+// The reply is a list of content blocks, only the text ones make up the message
+fn parse_response(body: &str) -> anyhow::Result<(String, Option<Usage>)> {
+    let response: CohereResponse = serde_json::from_str(body)?;
+    // Counts come back as floats, e.g. {"input_tokens": 812.0}, and billed units are what's charged
+    let usage = response
+        .usage
+        .and_then(|usage| usage.billed_units.or(usage.tokens))
+        .map(|units| Usage {
+            input_tokens: units.input_tokens as u32,
+            output_tokens: units.output_tokens as u32,
+        });
+    let text: String = response
+        .message
+        .content
+        .iter()
+        .filter(|content| content.content_type == "text")
+        .map(|content| content.text.as_str())
+        .collect();
+
+    if text.trim().is_empty() {
+        return Err(anyhow::anyhow!("No response from Cohere"));
+    }
+    Ok((text.trim().to_string(), usage))
+}
+
+#[async_trait::async_trait]
+impl GenerateCommitMessage for Client {
+    async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+        Ok(self.generate_with_usage(prompt).await?.0)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &Prompt,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        ai::ensure_online(self.http)?;
+        let request = ai::request_body(
+            &self.build_request(prompt),
+            &self.extra_fields,
+            RESERVED_FIELDS,
+        )?;
+        if self.log_request {
+            ai::log_request(self.model.provider(), &request, Some(&self.api_key));
+        }
+
+        let response = self
+            .http_client
+            .post("https://api.cohere.com/v2/chat")
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .header("Authorization", format!("Bearer {}", &self.api_key))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
+        if !status.is_success() {
+            if let Ok(error_response) = serde_json::from_str::<ErrorResponse>(&body) {
+                return Err(anyhow::anyhow!(
+                    "Cohere API error: {}",
+                    error_response.message
+                ));
+            } else {
+                return Err(anyhow::anyhow!("HTTP error {}: {}", status, body));
+            }
+        }
+
+        parse_response(&body)
+    }
+}
+
+pub async fn list_models(api_key: &str, http: HttpOptions) -> anyhow::Result<Vec<String>> {
+    ai::ensure_online(http)?;
+    let response = ai::http_client(http)
+        .get("https://api.cohere.com/v1/models")
+        .header("Accept", "application/json")
+        .header("Authorization", format!("Bearer {api_key}"))
+        .send()
+        .await?;
+
+    parse_models(&ai::model_list_body(response).await?)
+}
+
+fn parse_models(body: &str) -> anyhow::Result<Vec<String>> {
+    let list: CohereModelList = serde_json::from_str(body)?;
+    Ok(list
+        .models
+        .into_iter()
+        .filter(|model| model.endpoints.iter().any(|endpoint| endpoint == "chat"))
+        .map(|model| model.name)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::build_system_prompt;
+
+    fn test_prompt() -> Prompt {
+        Prompt {
+            system: build_system_prompt(&["always mention the ticket number"]),
+            user: "staged changes".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_request_shape() {
+        let client = Client::new(
+            "test-key".to_string(),
+            Model::CommandA,
+            &ClientOptions::default(),
+        );
+
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+
+        assert_eq!(body["model"], "command-a-03-2025");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert!(
+            body["messages"][0]["content"]
+                .as_str()
+                .unwrap()
+                .contains("always mention the ticket number")
+        );
+        assert_eq!(body["messages"][1]["role"], "user");
+        assert_eq!(body["messages"][1]["content"], "staged changes");
+        assert_eq!(body["max_tokens"], ai::DEFAULT_MAX_TOKENS);
+        assert_eq!(
+            body["stop_sequences"],
+            serde_json::json!(ai::STOP_SEQUENCES)
+        );
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let body = r#"{
+            "id": "c14c80c3-18eb-4519-9460-6c92edd8cfb4",
+            "finish_reason": "COMPLETE",
+            "message": {
+                "role": "assistant",
+                "content": [{"type": "text", "text": "feat(ai): add Cohere provider\n"}]
+            },
+            "usage": {"tokens": {"input_tokens": 120, "output_tokens": 9}}
+        }"#;
+
+        assert_eq!(
+            parse_response(body).unwrap(),
+            (
+                "feat(ai): add Cohere provider".to_string(),
+                Some(Usage {
+                    input_tokens: 120,
+                    output_tokens: 9
+                })
+            )
+        );
+        assert!(parse_response(r#"{"message": {"role": "assistant", "content": []}}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_models_list() {
+        let body = r#"{
+            "models": [
+                {"name": "command-a-03-2025", "endpoints": ["generate", "chat", "summarize"]},
+                {"name": "embed-english-v3.0", "endpoints": ["embed"]}
+            ]
+        }"#;
+
+        assert_eq!(parse_models(body).unwrap(), vec!["command-a-03-2025"]);
+    }
+}
@@ -1,11 +1,25 @@
 use serde::{Deserialize, Serialize};
 
-use crate::ai::{GenerateCommitMessage, Model, SYSTEM_PROMPT, build_user_prompt};
+use crate::ai::{
+    self, ClientOptions, ExtraFields, GenerateCommitMessage, HttpOptions, Model, Prompt, Usage,
+};
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+const RESERVED_FIELDS: &[&str] = &["contents", "system_instruction", "systemInstruction"];
 
 #[derive(Serialize)]
 struct GeminiRequest {
     system_instruction: Vec<Content>,
     contents: Vec<Content>,
+    #[serde(rename = "generationConfig", skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig>,
+}
+
+#[derive(Serialize)]
+struct GenerationConfig {
+    #[serde(rename = "maxOutputTokens")]
+    max_output_tokens: u32,
 }
 
 #[derive(Serialize)]
@@ -18,74 +32,172 @@ struct Part {
     text: String,
 }
 
+// Blocked prompts come back without candidates and safety stops without content
 #[derive(Deserialize)]
 struct GeminiResponse {
+    #[serde(default)]
     candidates: Vec<Candidate>,
+    #[serde(rename = "usageMetadata")]
+    usage_metadata: Option<UsageMetadata>,
 }
 
+// Thinking tokens are billed as output on top of the candidate tokens
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    thoughts_token_count: u32,
+}
+
+impl From<UsageMetadata> for Usage {
+    fn from(usage: UsageMetadata) -> Self {
+        Self {
+            input_tokens: usage.prompt_token_count,
+            output_tokens: usage.candidates_token_count + usage.thoughts_token_count,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct Candidate {
-    content: ContentResponse,
+    content: Option<ContentResponse>,
+    finish_reason: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct ContentResponse {
+    #[serde(default)]
     parts: Vec<PartResponse>,
 }
 
 #[derive(Deserialize)]
 struct PartResponse {
+    #[serde(default)]
     text: String,
 }
 
+fn response_text(response: GeminiResponse) -> anyhow::Result<String> {
+    let candidate = response
+        .candidates
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No candidates in Gemini response"))?;
+
+    let parts = candidate
+        .content
+        .map(|content| content.parts)
+        .unwrap_or_default();
+    if parts.is_empty() {
+        return Err(match candidate.finish_reason {
+            Some(reason) => {
+                anyhow::anyhow!("Gemini candidate has no content parts (finish reason: {reason})")
+            }
+            None => anyhow::anyhow!("Gemini candidate has no content parts"),
+        });
+    }
+
+    let text: String = parts.into_iter().map(|part| part.text).collect();
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(anyhow::anyhow!("Gemini returned empty text"));
+    }
+    Ok(text.to_string())
+}
+
 pub struct Client {
     api_key: String,
     model: Model,
+    base_url: String,
+    max_tokens: Option<u32>,
+    extra_fields: ExtraFields,
+    http: HttpOptions,
+    http_client: reqwest::Client,
+    log_request: bool,
 }
 
 impl Client {
-    pub fn new(api_key: String, model: Model) -> Self {
+    pub fn new(api_key: String, model: Model, options: &ClientOptions) -> Self {
         assert!(model.is_gemini(), "Model must be a Gemini model");
 
-        Self { api_key, model }
+        // Gemini 2.5 models spend output tokens on thinking, so only cap when asked to
+        Self {
+            api_key,
+            model,
+            base_url: options
+                .base_url
+                .clone()
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            max_tokens: options.max_tokens.filter(|max_tokens| *max_tokens > 0),
+            extra_fields: options.extra_fields.clone(),
+            http: options.http,
+            http_client: ai::http_client(options.http),
+            log_request: options.log_request,
+        }
     }
-}
 
-// TODO: Add system instruction
-// https://ai.google.dev/gemini-api/docs/text-generation
+    fn endpoint(&self) -> String {
+        ai::join_url(
+            &self.base_url,
+            &format!("models/{}:generateContent", self.model.to_api_str()),
+        )
+    }
 
-#[async_trait::async_trait]
-impl GenerateCommitMessage for Client {
-    async fn generate_commit_message(
-        &self,
-        files: &[String],
-        diff: &str,
-        context: Option<&str>,
-    ) -> anyhow::Result<String> {
-        let http_client = reqwest::Client::new();
-        let prompt = build_user_prompt(files, diff, context);
-
-        let request = GeminiRequest {
+    fn build_request(&self, prompt: &Prompt) -> GeminiRequest {
+        GeminiRequest {
             system_instruction: vec![Content {
                 parts: vec![Part {
-                    text: SYSTEM_PROMPT.to_string(),
+                    text: prompt.system.clone(),
                 }],
             }],
             contents: vec![Content {
-                parts: vec![Part { text: prompt }],
+                parts: vec![Part {
+                    text: prompt.user.clone(),
+                }],
             }],
-        };
+            generation_config: self
+                .max_tokens
+                .map(|max_output_tokens| GenerationConfig { max_output_tokens }),
+        }
+    }
+}
+
+// TODO: Add system instruction
+// https://ai.google.dev/gemini-api/docs/text-generation
+
+#[async_trait::async_trait]
+impl GenerateCommitMessage for Client {
+    async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+        Ok(self.generate_with_usage(prompt).await?.0)
+    }
 
-        let response = http_client
-            .post(format!(
-                "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
-                self.model.to_api_str()
-            ))
+    async fn generate_with_usage(
+        &self,
+        prompt: &Prompt,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        ai::ensure_online(self.http)?;
+        let request = ai::request_body(
+            &self.build_request(prompt),
+            &self.extra_fields,
+            RESERVED_FIELDS,
+        )?;
+        if self.log_request {
+            ai::log_request(self.model.provider(), &request, Some(&self.api_key));
+        }
+
+        let response = self
+            .http_client
+            .post(self.endpoint())
             .header("content-type", "application/json")
             .header("x-goog-api-key", &self.api_key)
             .json(&request)
             .send()
-            .await?;
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -93,19 +205,219 @@ impl GenerateCommitMessage for Client {
             return Err(anyhow::anyhow!("HTTP error {}: {}", status, error_text));
         }
 
-        let gemini_response: GeminiResponse = response.json().await?;
-
-        if let Some(content) = gemini_response
-            .candidates
-            .first()
-            .ok_or(anyhow::anyhow!("No candidates in Gemini response"))?
-            .content
-            .parts
-            .first()
-        {
-            Ok(content.text.trim().to_string())
-        } else {
-            Err(anyhow::anyhow!("No response from Claude"))
+        let mut gemini_response: GeminiResponse = response
+            .json()
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
+        let usage = gemini_response.usage_metadata.take().map(Usage::from);
+        Ok((response_text(gemini_response)?, usage))
+    }
+}
+
+#[derive(Deserialize)]
+struct GeminiModelList {
+    models: Vec<GeminiModel>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModel {
+    name: String,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+pub async fn list_models(
+    api_key: &str,
+    base_url: Option<&str>,
+    http: HttpOptions,
+) -> anyhow::Result<Vec<String>> {
+    ai::ensure_online(http)?;
+    let response = ai::http_client(http)
+        .get(ai::join_url(base_url.unwrap_or(DEFAULT_BASE_URL), "models"))
+        .header("x-goog-api-key", api_key)
+        .send()
+        .await?;
+
+    parse_models(&ai::model_list_body(response).await?)
+}
+
+// Names come back as "models/<id>", and embedding models can't generate text
+fn parse_models(body: &str) -> anyhow::Result<Vec<String>> {
+    let list: GeminiModelList = serde_json::from_str(body)?;
+    Ok(list
+        .models
+        .into_iter()
+        .filter(|model| {
+            model.supported_generation_methods.is_empty()
+                || model
+                    .supported_generation_methods
+                    .iter()
+                    .any(|method| method == "generateContent")
+        })
+        .map(|model| {
+            model
+                .name
+                .strip_prefix("models/")
+                .unwrap_or(&model.name)
+                .to_string()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::build_system_prompt;
+
+    fn test_prompt() -> Prompt {
+        Prompt {
+            system: build_system_prompt(&["always mention the ticket number"]),
+            user: "staged changes".to_string(),
         }
     }
+
+    fn parse_response(body: &str) -> anyhow::Result<String> {
+        response_text(serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn test_response_errors_name_gemini() {
+        let error = parse_response(
+            r#"{"candidates": [{"content": {"parts": [], "role": "model"}, "finishReason": "MAX_TOKENS"}]}"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("Gemini"));
+        assert!(!error.contains("Claude"));
+        assert!(error.contains("no content parts"));
+        assert!(error.contains("MAX_TOKENS"));
+
+        let error = parse_response(r#"{"promptFeedback": {"blockReason": "SAFETY"}}"#).unwrap_err();
+        assert_eq!(error.to_string(), "No candidates in Gemini response");
+
+        let error = parse_response(r#"{"candidates": [{"content": {"parts": [{"text": "  "}]}}]}"#)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Gemini returned empty text");
+
+        assert_eq!(
+            parse_response(
+                r#"{"candidates": [{"content": {"parts": [{"text": "feat: add x\n"}]}}]}"#
+            )
+            .unwrap(),
+            "feat: add x"
+        );
+    }
+
+    #[test]
+    fn test_usage_metadata_counts_thinking_as_output() {
+        let response: GeminiResponse = serde_json::from_str(
+            r#"{"candidates": [], "usageMetadata": {"promptTokenCount": 900, "candidatesTokenCount": 20, "thoughtsTokenCount": 100}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            response.usage_metadata.map(Usage::from),
+            Some(Usage {
+                input_tokens: 900,
+                output_tokens: 120
+            })
+        );
+    }
+
+    #[test]
+    fn test_endpoint_joins_base_url() {
+        let endpoint = |base_url: Option<&str>| {
+            let options = ClientOptions {
+                base_url: base_url.map(str::to_string),
+                ..Default::default()
+            };
+            Client::new("test-key".to_string(), Model::Gemini2_5Flash, &options).endpoint()
+        };
+
+        let expected = format!(
+            "https://gateway.example.com/gemini/models/{}:generateContent",
+            Model::Gemini2_5Flash.to_api_str()
+        );
+        assert_eq!(
+            endpoint(Some("https://gateway.example.com/gemini")),
+            expected
+        );
+        assert_eq!(
+            endpoint(Some("https://gateway.example.com/gemini/")),
+            expected
+        );
+        assert_eq!(
+            endpoint(Some("https://gateway.example.com/gemini//")),
+            expected
+        );
+        assert_eq!(
+            endpoint(None),
+            format!(
+                "{DEFAULT_BASE_URL}/models/{}:generateContent",
+                Model::Gemini2_5Flash.to_api_str()
+            )
+        );
+    }
+
+    #[test]
+    fn test_request_carries_system_and_user_prompt() {
+        let client = Client::new(
+            "test-key".to_string(),
+            Model::Gemini2_5Flash,
+            &ClientOptions::default(),
+        );
+
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+
+        assert!(
+            body["system_instruction"][0]["parts"][0]["text"]
+                .as_str()
+                .unwrap()
+                .contains("always mention the ticket number")
+        );
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "staged changes");
+    }
+
+    #[test]
+    fn test_request_only_caps_tokens_when_explicit() {
+        let client = Client::new(
+            "test-key".to_string(),
+            Model::Gemini2_5Flash,
+            &ClientOptions {
+                body: true,
+                ..Default::default()
+            },
+        );
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+        assert!(body.get("generationConfig").is_none());
+
+        let client = Client::new(
+            "test-key".to_string(),
+            Model::Gemini2_5Flash,
+            &ClientOptions {
+                max_tokens: Some(300),
+                ..Default::default()
+            },
+        );
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 300);
+    }
+
+    #[test]
+    fn test_parse_models_list() {
+        let body = r#"{
+            "models": [
+                {"name": "models/gemini-2.5-flash", "supportedGenerationMethods": ["generateContent", "countTokens"]},
+                {"name": "models/text-embedding-004", "supportedGenerationMethods": ["embedContent"]},
+                {"name": "models/gemini-2.5-pro"}
+            ]
+        }"#;
+
+        assert_eq!(
+            parse_models(body).unwrap(),
+            vec!["gemini-2.5-flash", "gemini-2.5-pro"]
+        );
+        assert!(parse_models("{}").is_err());
+    }
 }
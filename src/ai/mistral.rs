@@ -1,12 +1,19 @@
-use crate::ai::{self, GenerateCommitMessage, Model, SYSTEM_PROMPT, build_user_prompt};
+use crate::ai::{
+    self, ClientOptions, ExtraFields, GenerateCommitMessage, HttpOptions, Model, Prompt, TokenSink,
+    Usage,
+};
 use serde::{Deserialize, Serialize};
 
+const RESERVED_FIELDS: &[&str] = &["model", "messages"];
+
 #[derive(Serialize)]
 struct MistralRequest {
     model: String,
     messages: Vec<Message>,
-    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
     temperature: f32,
+    stop: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -18,6 +25,7 @@ struct Message {
 #[derive(Deserialize)]
 struct MistralResponse {
     choices: Vec<Choice>,
+    usage: Option<ai::ChatUsage>,
 }
 
 #[derive(Deserialize)]
@@ -43,51 +51,67 @@ struct ErrorDetail {
 pub struct Client {
     api_key: String,
     model: ai::Model,
+    max_tokens: Option<u32>,
+    extra_fields: ExtraFields,
+    http: HttpOptions,
+    http_client: reqwest::Client,
+    log_request: bool,
 }
 
 impl Client {
-    pub fn new(api_key: String, model: Model) -> Self {
+    pub fn new(api_key: String, model: Model, options: &ClientOptions) -> Self {
         assert!(model.is_mistral(), "Model must be a Mistral model");
 
-        Self { api_key, model }
+        Self {
+            api_key,
+            model,
+            max_tokens: options.resolved_max_tokens(),
+            extra_fields: options.extra_fields.clone(),
+            http: options.http,
+            http_client: ai::http_client(options.http),
+            log_request: options.log_request,
+        }
     }
-}
 
-#[async_trait::async_trait]
-impl GenerateCommitMessage for Client {
-    async fn generate_commit_message(
-        &self,
-        files: &[String],
-        diff: &str,
-        context: Option<&str>,
-    ) -> anyhow::Result<String> {
-        let http_client = reqwest::Client::new();
-        let prompt = build_user_prompt(files, diff, context);
-
-        let request = MistralRequest {
+    fn build_request(&self, prompt: &Prompt) -> MistralRequest {
+        MistralRequest {
             model: self.model.to_api_str(),
             messages: vec![
                 Message {
                     role: "system".to_string(),
-                    content: SYSTEM_PROMPT.to_string(),
+                    content: prompt.system.clone(),
                 },
                 Message {
                     role: "user".to_string(),
-                    content: prompt,
+                    content: prompt.user.clone(),
                 },
             ],
-            max_tokens: 1024,
+            max_tokens: self.max_tokens,
+            stop: ai::stop_sequences(),
             temperature: 0.3,
-        };
+        }
+    }
 
-        let response = http_client
+    async fn send(&self, request: &serde_json::Value) -> anyhow::Result<reqwest::Response> {
+        ai::ensure_online(self.http)?;
+        if self.log_request {
+            ai::log_request(self.model.provider(), request, Some(&self.api_key));
+        }
+        let accept = if request["stream"] == true {
+            "text/event-stream"
+        } else {
+            "application/json"
+        };
+        let response = self
+            .http_client
             .post("https://api.mistral.ai/v1/chat/completions")
             .header("Content-Type", "application/json")
-            .header("Accept", "application/json")
+            .header("Accept", accept)
             .header("Authorization", format!("Bearer {}", &self.api_key))
-            .json(&request)
+            .json(request)
             .send()
-            .await?;
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -101,13 +125,138 @@ impl GenerateCommitMessage for Client {
                 return Err(anyhow::anyhow!("HTTP error {}: {}", status, error_text));
             }
         }
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl GenerateCommitMessage for Client {
+    async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+        Ok(self.generate_with_usage(prompt).await?.0)
+    }
 
-        let mistral_response: MistralResponse = response.json().await?;
+    async fn generate_with_usage(
+        &self,
+        prompt: &Prompt,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        let request = ai::request_body(
+            &self.build_request(prompt),
+            &self.extra_fields,
+            RESERVED_FIELDS,
+        )?;
+        let response = self.send(&request).await?;
+
+        let mistral_response: MistralResponse = response
+            .json()
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
 
+        let usage = mistral_response.usage.map(Usage::from);
         if let Some(choice) = mistral_response.choices.first() {
-            Ok(choice.message.content.trim().to_string())
+            Ok((choice.message.content.trim().to_string(), usage))
         } else {
             Err(anyhow::anyhow!("No response from Mistral"))
         }
     }
+
+    async fn generate_streaming(
+        &self,
+        prompt: &Prompt,
+        on_token: &TokenSink<'_>,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        let mut request = ai::request_body(
+            &self.build_request(prompt),
+            &self.extra_fields,
+            RESERVED_FIELDS,
+        )?;
+        request["stream"] = serde_json::Value::Bool(true);
+        let response = self.send(&request).await?;
+
+        let mut message = String::new();
+        let mut usage = None;
+        ai::read_sse(response, self.model.provider(), self.http, |data| {
+            if let Some(token) = ai::read_chat_chunk(data, &mut usage)? {
+                on_token(&token);
+                message.push_str(&token);
+            }
+            Ok(())
+        })
+        .await?;
+
+        match message.trim() {
+            "" => Err(anyhow::anyhow!("No response from Mistral")),
+            message => Ok((message.to_string(), usage)),
+        }
+    }
+}
+
+pub async fn list_models(api_key: &str, http: HttpOptions) -> anyhow::Result<Vec<String>> {
+    ai::ensure_online(http)?;
+    let response = ai::http_client(http)
+        .get("https://api.mistral.ai/v1/models")
+        .header("Accept", "application/json")
+        .header("Authorization", format!("Bearer {api_key}"))
+        .send()
+        .await?;
+
+    ai::parse_model_list(&ai::model_list_body(response).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::build_system_prompt;
+
+    fn test_prompt() -> Prompt {
+        Prompt {
+            system: build_system_prompt(&["always mention the ticket number"]),
+            user: "staged changes".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_request_carries_system_and_user_prompt() {
+        let client = Client::new(
+            "test-key".to_string(),
+            Model::MistralSmall3_2,
+            &ClientOptions::default(),
+        );
+
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert!(
+            body["messages"][0]["content"]
+                .as_str()
+                .unwrap()
+                .contains("always mention the ticket number")
+        );
+        assert_eq!(body["messages"][1]["content"], "staged changes");
+    }
+
+    #[test]
+    fn test_request_omits_max_tokens_when_uncapped() {
+        let options = ClientOptions {
+            max_tokens: Some(0),
+            ..Default::default()
+        };
+        let client = Client::new("test-key".to_string(), Model::MistralSmall3_2, &options);
+
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+
+        assert!(body.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn test_request_serializes_stop_sequences() {
+        let client = Client::new(
+            "test-key".to_string(),
+            Model::MistralSmall3_2,
+            &ClientOptions::default(),
+        );
+
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+
+        assert_eq!(body["stop"], serde_json::json!(ai::STOP_SEQUENCES));
+    }
 }
@@ -0,0 +1,305 @@
+use crate::ai::{
+    self, ClientOptions, ExtraFields, GenerateCommitMessage, HttpOptions, Model, Prompt, Usage,
+};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_HOST: &str = "http://localhost:11434";
+const HOST_ENV_VAR: &str = "OLLAMA_HOST";
+const RESERVED_FIELDS: &[&str] = &["model", "messages", "stream"];
+
+#[derive(Serialize)]
+struct OllamaRequest {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    options: RequestOptions,
+}
+
+#[derive(Serialize)]
+struct RequestOptions {
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    stop: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    message: Option<ResponseMessage>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Deserialize)]
+struct TagList {
+    models: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    name: String,
+}
+
+// OLLAMA_HOST is often set the way the server reads it, e.g. "0.0.0.0:11434" without a scheme
+fn normalize_host(host: &str) -> String {
+    let host = host.trim().trim_end_matches('/');
+    if host.contains("://") {
+        host.to_string()
+    } else {
+        format!("http://{host}")
+    }
+}
+
+pub fn host() -> String {
+    std::env::var(HOST_ENV_VAR)
+        .ok()
+        .filter(|host| !host.trim().is_empty())
+        .map(|host| normalize_host(&host))
+        .unwrap_or_else(|| DEFAULT_HOST.to_string())
+}
+
+pub struct Client {
+    model: Model,
+    host: String,
+    max_tokens: Option<u32>,
+    extra_fields: ExtraFields,
+    http: HttpOptions,
+    http_client: reqwest::Client,
+    log_request: bool,
+}
+
+impl Client {
+    pub fn new(model: Model, options: &ClientOptions) -> Self {
+        assert!(model.is_ollama(), "Model must be an Ollama model");
+
+        Self {
+            model,
+            host: options.base_url.clone().unwrap_or_else(host),
+            max_tokens: options.resolved_max_tokens(),
+            extra_fields: options.extra_fields.clone(),
+            http: options.http,
+            http_client: ai::http_client(options.http),
+            log_request: options.log_request,
+        }
+    }
+
+    fn endpoint(&self) -> String {
+        ai::join_url(&self.host, "api/chat")
+    }
+
+    fn build_request(&self, prompt: &Prompt) -> OllamaRequest {
+        OllamaRequest {
+            model: self.model.to_api_str(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: prompt.system.clone(),
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: prompt.user.clone(),
+                },
+            ],
+            stream: false,
+            options: RequestOptions {
+                temperature: 0.3,
+                num_predict: self.max_tokens,
+                stop: ai::stop_sequences(),
+            },
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl GenerateCommitMessage for Client {
+    async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+        Ok(self.generate_with_usage(prompt).await?.0)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &Prompt,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        ai::ensure_online(self.http)?;
+        let request = ai::request_body(
+            &self.build_request(prompt),
+            &self.extra_fields,
+            RESERVED_FIELDS,
+        )?;
+        if self.log_request {
+            ai::log_request(self.model.provider(), &request, None);
+        }
+
+        let response = self
+            .http_client
+            .post(self.endpoint())
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| {
+                if err.is_timeout() {
+                    ai::request_error(self.model.provider(), self.http, err)
+                } else {
+                    anyhow::anyhow!("Could not reach Ollama at {} ({err})", self.host)
+                }
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            if let Ok(error_response) = serde_json::from_str::<ErrorResponse>(&error_text) {
+                return Err(anyhow::anyhow!("Ollama error: {}", error_response.error));
+            } else {
+                return Err(anyhow::anyhow!("HTTP error {}: {}", status, error_text));
+            }
+        }
+
+        let ollama_response: OllamaResponse = response
+            .json()
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
+        let usage = match (
+            ollama_response.prompt_eval_count,
+            ollama_response.eval_count,
+        ) {
+            (Some(input_tokens), Some(output_tokens)) => Some(Usage {
+                input_tokens,
+                output_tokens,
+            }),
+            _ => None,
+        };
+
+        match ollama_response.message {
+            Some(message) => Ok((message.content.trim().to_string(), usage)),
+            None => Err(anyhow::anyhow!("No response from Ollama")),
+        }
+    }
+}
+
+// Ollama lists pulled models under /api/tags rather than an OpenAI-style /models
+pub async fn list_models(base_url: Option<&str>, http: HttpOptions) -> anyhow::Result<Vec<String>> {
+    let host = base_url.map(str::to_string).unwrap_or_else(host);
+    ai::ensure_online(http)?;
+    let response = ai::http_client(http)
+        .get(ai::join_url(&host, "api/tags"))
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    let tags: TagList = serde_json::from_str(&ai::model_list_body(response).await?)?;
+    Ok(tags.models.into_iter().map(|tag| tag.name).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_prompt() -> Prompt {
+        Prompt {
+            system: "system prompt".to_string(),
+            user: "staged changes".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_request_disables_streaming_and_caps_output() {
+        let client = Client::new(Model::OllamaLlama3, &ClientOptions::default());
+
+        let body = serde_json::to_value(client.build_request(&test_prompt())).unwrap();
+
+        assert_eq!(body["model"], "llama3");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "staged changes");
+        assert_eq!(body["options"]["num_predict"], ai::DEFAULT_MAX_TOKENS);
+        assert_eq!(
+            body["options"]["stop"],
+            serde_json::json!(ai::STOP_SEQUENCES)
+        );
+    }
+
+    #[test]
+    fn test_endpoint_uses_base_url_override() {
+        let options = ClientOptions {
+            base_url: Some("http://gpu-box:11434/".to_string()),
+            ..Default::default()
+        };
+        let client = Client::new(Model::OllamaQwen2_5Coder, &options);
+
+        assert_eq!(client.endpoint(), "http://gpu-box:11434/api/chat");
+    }
+
+    #[tokio::test]
+    async fn test_no_network_refuses_request() {
+        let options = ClientOptions {
+            http: HttpOptions {
+                offline: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let client = Client::new(Model::OllamaLlama3, &options);
+
+        let error = client
+            .generate_commit_message(&test_prompt())
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("--no-network"));
+        assert!(
+            list_models(None, options.http)
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("--no-network")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hung_server_times_out() {
+        // Connections complete in the backlog but nothing ever answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let options = ClientOptions {
+            base_url: Some(format!("http://{}", listener.local_addr().unwrap())),
+            http: HttpOptions {
+                timeout_secs: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let client = Client::new(Model::OllamaLlama3, &options);
+
+        let error = client
+            .generate_commit_message(&test_prompt())
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Request to Ollama timed out after 1s");
+    }
+
+    #[test]
+    fn test_normalize_host_adds_missing_scheme() {
+        assert_eq!(normalize_host("0.0.0.0:11434"), "http://0.0.0.0:11434");
+        assert_eq!(
+            normalize_host("https://ollama.example.com/"),
+            "https://ollama.example.com"
+        );
+    }
+}
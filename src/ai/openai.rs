@@ -1,12 +1,22 @@
 use reqwest::header::AUTHORIZATION;
 use serde::{Deserialize, Serialize};
 
-use crate::ai::{GenerateCommitMessage, Model, SYSTEM_PROMPT, build_user_prompt};
+use crate::ai::{
+    self, ClientOptions, ExtraFields, GenerateCommitMessage, HttpOptions, Model, Prompt, TokenSink,
+    Usage,
+};
+use crate::commit::ConventionalCommit;
 
 #[derive(Serialize)]
 pub struct OpenAIRequest {
     model: String,
     messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
@@ -18,6 +28,7 @@ pub struct Message {
 #[derive(Deserialize)]
 pub struct OpenAIResponse {
     choices: Vec<Choice>,
+    usage: Option<ai::ChatUsage>,
 }
 
 #[derive(Deserialize)]
@@ -30,6 +41,15 @@ pub struct MessageContent {
     content: String,
 }
 
+#[derive(Deserialize)]
+struct StructuredCommit {
+    #[serde(rename = "type")]
+    commit_type: String,
+    scope: Option<String>,
+    description: String,
+    body: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct ErrorResponse {
     error: ErrorDetail,
@@ -40,71 +60,545 @@ struct ErrorDetail {
     message: String,
 }
 
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+pub const LLAMACPP_DEFAULT_BASE_URL: &str = "http://localhost:8080/v1";
+
+const RESERVED_FIELDS: &[&str] = &["model", "messages"];
+
+fn commit_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": "conventional_commit",
+            "strict": true,
+            "schema": {
+                "type": "object",
+                "properties": {
+                    "type": { "type": "string" },
+                    "scope": { "type": ["string", "null"] },
+                    "description": { "type": "string" },
+                    "body": { "type": ["string", "null"] }
+                },
+                "required": ["type", "scope", "description", "body"],
+                "additionalProperties": false
+            }
+        }
+    })
+}
+
+fn assemble_structured(content: &str) -> anyhow::Result<String> {
+    let structured: StructuredCommit = serde_json::from_str(content)
+        .map_err(|err| anyhow::anyhow!("Structured response was not valid JSON: {err}"))?;
+
+    let commit = ConventionalCommit {
+        commit_type: structured.commit_type.trim().to_string(),
+        scope: structured
+            .scope
+            .map(|scope| scope.trim().to_string())
+            .filter(|scope| !scope.is_empty()),
+        breaking: false,
+        description: structured.description.trim().to_string(),
+        body: structured
+            .body
+            .map(|body| body.trim().to_string())
+            .filter(|body| !body.is_empty()),
+        footers: Vec::new(),
+    };
+
+    Ok(commit.to_string())
+}
+
 pub struct Client {
-    api_key: String,
+    api_key: Option<String>,
     model: Model,
+    model_name: String,
+    base_url: String,
+    max_tokens: Option<u32>,
+    extra_fields: ExtraFields,
+    http: HttpOptions,
+    http_client: reqwest::Client,
+    log_request: bool,
+    structured: bool,
 }
 
 impl Client {
-    pub fn new(api_key: String, model: Model) -> Self {
-        assert!(model.is_openai(), "Model must be an OpenAI model");
+    pub fn new(api_key: Option<String>, model: Model, options: &ClientOptions) -> Self {
+        assert!(
+            model.is_openai() || model.is_local(),
+            "Model must be an OpenAI-compatible model"
+        );
+
+        let default_base_url = if model.is_local() {
+            LLAMACPP_DEFAULT_BASE_URL
+        } else {
+            DEFAULT_BASE_URL
+        };
 
-        Self { api_key, model }
+        // Reasoning models share this budget with hidden reasoning, so only cap when asked to
+        Self {
+            api_key,
+            model_name: options
+                .model_name
+                .clone()
+                .unwrap_or_else(|| model.to_api_str()),
+            model,
+            base_url: options
+                .base_url
+                .clone()
+                .unwrap_or_else(|| default_base_url.to_string()),
+            max_tokens: options.max_tokens.filter(|max_tokens| *max_tokens > 0),
+            extra_fields: options.extra_fields.clone(),
+            http: options.http,
+            http_client: ai::http_client(options.http),
+            log_request: options.log_request,
+            structured: options.structured,
+        }
     }
-}
 
-#[async_trait::async_trait]
-impl GenerateCommitMessage for Client {
-    async fn generate_commit_message(
+    fn completions_url(&self) -> String {
+        format!("{}/chat/completions", self.base_url.trim_end_matches('/'))
+    }
+
+    fn build_http_request(
         &self,
-        files: &[String],
-        diff: &str,
-        context: Option<&str>,
-    ) -> anyhow::Result<String> {
-        let http_client = reqwest::Client::new();
-        let prompt = build_user_prompt(files, diff, context);
-
-        let request = OpenAIRequest {
-            model: self.model.to_api_str(),
+        http_client: &reqwest::Client,
+        request: &serde_json::Value,
+    ) -> reqwest::RequestBuilder {
+        let builder = http_client
+            .post(self.completions_url())
+            .header("content-type", "application/json");
+
+        let builder = match self.api_key.as_deref().filter(|key| !key.is_empty()) {
+            Some(api_key) => builder.header(AUTHORIZATION, format!("Bearer {}", api_key)),
+            None => builder,
+        };
+
+        builder.json(request)
+    }
+
+    fn build_body(&self, prompt: &Prompt) -> anyhow::Result<serde_json::Value> {
+        ai::request_body(
+            &self.build_request(prompt),
+            &self.extra_fields,
+            RESERVED_FIELDS,
+        )
+    }
+
+    fn build_request(&self, prompt: &Prompt) -> OpenAIRequest {
+        OpenAIRequest {
+            model: self.model_name.clone(),
             messages: vec![
                 Message {
                     role: "system".to_string(),
-                    content: SYSTEM_PROMPT.to_string(),
+                    content: prompt.system.clone(),
                 },
                 Message {
                     role: "user".to_string(),
-                    content: prompt,
+                    content: prompt.user.clone(),
                 },
             ],
-        };
+            max_completion_tokens: self.max_tokens,
+            // OpenAI's reasoning models reject `stop`, local servers honor it
+            stop: self.model.is_local().then(ai::stop_sequences),
+            response_format: self.structured.then(commit_schema),
+        }
+    }
 
-        let response = http_client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("content-type", "application/json")
-            .header(AUTHORIZATION, format!("Bearer {}", self.api_key))
-            .json(&request)
+    async fn send(&self, request: &serde_json::Value) -> anyhow::Result<reqwest::Response> {
+        ai::ensure_online(self.http)?;
+        if self.log_request {
+            ai::log_request(self.model.provider(), request, self.api_key.as_deref());
+        }
+        let response = self
+            .build_http_request(&self.http_client, request)
             .send()
-            .await?;
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await?;
-            if let Ok(error_response) = serde_json::from_str::<ErrorResponse>(&error_text) {
-                return Err(anyhow::anyhow!(
-                    "Claude API error: {}",
-                    error_response.error.message
-                ));
-            } else {
-                return Err(anyhow::anyhow!("HTTP error {}: {}", status, error_text));
-            }
+            return Err(api_error(status, &error_text));
         }
+        Ok(response)
+    }
+}
 
-        let openai_response: OpenAIResponse = response.json().await?;
+#[async_trait::async_trait]
+impl GenerateCommitMessage for Client {
+    async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+        Ok(self.generate_with_usage(prompt).await?.0)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &Prompt,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        let request = self.build_body(prompt)?;
+        let response = self.send(&request).await?;
 
+        let openai_response: OpenAIResponse = response
+            .json()
+            .await
+            .map_err(|err| ai::request_error(self.model.provider(), self.http, err))?;
+
+        let usage = openai_response.usage.map(Usage::from);
         if let Some(choice) = openai_response.choices.first() {
-            Ok(choice.message.content.trim().to_string())
+            if self.structured {
+                return Ok((assemble_structured(&choice.message.content)?, usage));
+            }
+            Ok((choice.message.content.trim().to_string(), usage))
         } else {
-            Err(anyhow::anyhow!("No response from Claude"))
+            Err(anyhow::anyhow!("No response from OpenAI"))
+        }
+    }
+
+    async fn generate_streaming(
+        &self,
+        prompt: &Prompt,
+        on_token: &TokenSink<'_>,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        let mut request = self.build_body(prompt)?;
+        request["stream"] = serde_json::Value::Bool(true);
+        // Without this the stream carries no token counts at all
+        request["stream_options"] = serde_json::json!({ "include_usage": true });
+        let response = self.send(&request).await?;
+
+        let mut message = String::new();
+        let mut usage = None;
+        ai::read_sse(response, self.model.provider(), self.http, |data| {
+            if let Some(token) = ai::read_chat_chunk(data, &mut usage)? {
+                on_token(&token);
+                message.push_str(&token);
+            }
+            Ok(())
+        })
+        .await?;
+
+        if message.trim().is_empty() {
+            return Err(anyhow::anyhow!("No response from OpenAI"));
         }
+        if self.structured {
+            return Ok((assemble_structured(&message)?, usage));
+        }
+        Ok((message.trim().to_string(), usage))
+    }
+}
+
+fn api_error(status: reqwest::StatusCode, error_text: &str) -> anyhow::Error {
+    match serde_json::from_str::<ErrorResponse>(error_text) {
+        Ok(error_response) => anyhow::anyhow!("OpenAI API error: {}", error_response.error.message),
+        Err(_) => anyhow::anyhow!("HTTP error {}: {}", status, error_text),
+    }
+}
+
+pub async fn list_models(
+    api_key: Option<&str>,
+    base_url: Option<&str>,
+    http: HttpOptions,
+) -> anyhow::Result<Vec<String>> {
+    let base_url = base_url.unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/');
+    ai::ensure_online(http)?;
+    let mut request = ai::http_client(http).get(format!("{base_url}/models"));
+    if let Some(api_key) = api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    ai::parse_model_list(&ai::model_list_body(request.send().await?).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::build_system_prompt;
+
+    fn test_prompt() -> Prompt {
+        Prompt {
+            system: build_system_prompt(&["always mention the ticket number"]),
+            user: "staged changes".to_string(),
+        }
+    }
+
+    fn sample_request(client: &Client) -> serde_json::Value {
+        client.build_body(&test_prompt()).unwrap()
+    }
+
+    #[test]
+    fn test_request_carries_system_and_user_prompt() {
+        let client = Client::new(None, Model::Gpt5, &ClientOptions::default());
+
+        let body = sample_request(&client);
+
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert!(
+            body["messages"][0]["content"]
+                .as_str()
+                .unwrap()
+                .contains("always mention the ticket number")
+        );
+        assert_eq!(body["messages"][1]["content"], "staged changes");
+    }
+
+    #[test]
+    fn test_request_includes_auth_when_key_is_configured() {
+        let client = Client::new(
+            Some("test-key".to_string()),
+            Model::Gpt5,
+            &ClientOptions::default(),
+        );
+
+        let request = client
+            .build_http_request(&reqwest::Client::new(), &sample_request(&client))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            "https://api.openai.com/v1/chat/completions"
+        );
+        assert_eq!(
+            request.headers().get(AUTHORIZATION).unwrap(),
+            "Bearer test-key"
+        );
+    }
+
+    #[test]
+    fn test_local_request_omits_auth_without_key() {
+        let client = Client::new(
+            None,
+            Model::Local("llama-3".to_string()),
+            &ClientOptions {
+                base_url: Some("http://localhost:9000/v1/".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let request = client
+            .build_http_request(&reqwest::Client::new(), &sample_request(&client))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            "http://localhost:9000/v1/chat/completions"
+        );
+        assert!(request.headers().get(AUTHORIZATION).is_none());
+    }
+
+    #[test]
+    fn test_custom_endpoint_with_model_name_override() {
+        let client = Client::new(
+            Some("token".to_string()),
+            Model::Gpt5,
+            &ClientOptions {
+                base_url: Some("http://my-server:8000/v1".to_string()),
+                model_name: Some("Qwen/Qwen2.5-Coder-32B-Instruct".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let body = sample_request(&client);
+        let request = client
+            .build_http_request(&reqwest::Client::new(), &body)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            "http://my-server:8000/v1/chat/completions"
+        );
+        assert_eq!(body["model"], "Qwen/Qwen2.5-Coder-32B-Instruct");
+    }
+
+    // Answers one request with the given SSE body and hands back what the client sent
+    fn serve_sse(body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .map_or(0, |length| length.parse().unwrap());
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{body}"
+            )
+            .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (base_url, server)
+    }
+
+    #[tokio::test]
+    async fn test_streaming_accumulates_chunks() {
+        let (base_url, server) = serve_sse(concat!(
+            "data: {\"choices\": [{\"delta\": {\"role\": \"assistant\"}}]}\n\n",
+            "data: {\"choices\": [{\"delta\": {\"content\": \"feat(cli): \"}}]}\n\n",
+            "data: {\"choices\": [{\"delta\": {\"content\": \"add --stream\\n\\n\"}}]}\n\n",
+            "data: {\"choices\": [], \"usage\": {\"prompt_tokens\": 812, \"completion_tokens\": 6}}\n\n",
+            "data: [DONE]\n\n",
+        ));
+        let options = ClientOptions {
+            base_url: Some(base_url),
+            ..Default::default()
+        };
+        let client = Client::new(None, Model::Local("llama-3".to_string()), &options);
+        let tokens = std::sync::Mutex::new(Vec::new());
+        let sink = |token: &str| tokens.lock().unwrap().push(token.to_string());
+
+        let (message, usage) = client
+            .generate_streaming(&test_prompt(), &sink)
+            .await
+            .unwrap();
+
+        assert_eq!(message, "feat(cli): add --stream");
+        assert_eq!(
+            usage,
+            Some(Usage {
+                input_tokens: 812,
+                output_tokens: 6
+            })
+        );
+        assert_eq!(
+            *tokens.lock().unwrap(),
+            vec!["feat(cli): ", "add --stream\n\n"]
+        );
+        let request = server.join().unwrap();
+        assert!(request.contains("\"stream\":true"));
+        assert!(request.contains("\"include_usage\":true"));
+    }
+
+    #[test]
+    fn test_api_error_names_openai() {
+        let error = api_error(
+            reqwest::StatusCode::UNAUTHORIZED,
+            r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error"}}"#,
+        );
+        assert_eq!(
+            error.to_string(),
+            "OpenAI API error: Incorrect API key provided"
+        );
+
+        let error = api_error(reqwest::StatusCode::BAD_GATEWAY, "upstream timed out");
+        assert_eq!(
+            error.to_string(),
+            "HTTP error 502 Bad Gateway: upstream timed out"
+        );
+    }
+
+    #[test]
+    fn test_request_only_caps_tokens_when_explicit() {
+        let client = Client::new(None, Model::Gpt5, &ClientOptions::default());
+        let body = sample_request(&client);
+        assert!(body.get("max_completion_tokens").is_none());
+
+        let options = ClientOptions {
+            max_tokens: Some(4096),
+            ..Default::default()
+        };
+        let client = Client::new(None, Model::Gpt5, &options);
+        let body = sample_request(&client);
+        assert_eq!(body["max_completion_tokens"], 4096);
+    }
+
+    #[test]
+    fn test_stop_sequences_only_sent_to_local_models() {
+        let client = Client::new(None, Model::Gpt5, &ClientOptions::default());
+        let body = sample_request(&client);
+        assert!(body.get("stop").is_none());
+
+        let client = Client::new(
+            None,
+            Model::Local("llama-3".to_string()),
+            &ClientOptions::default(),
+        );
+        let body = sample_request(&client);
+        assert_eq!(body["stop"], serde_json::json!(ai::STOP_SEQUENCES));
+    }
+
+    #[test]
+    fn test_extra_request_fields_are_sent() {
+        let options = ClientOptions {
+            extra_fields: serde_json::from_value(serde_json::json!({
+                "top_p": 0.5,
+                "frequency_penalty": 0.2
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        let client = Client::new(None, Model::Gpt5, &options);
+
+        let body = sample_request(&client);
+
+        assert_eq!(body["top_p"], 0.5);
+        assert_eq!(body["frequency_penalty"], 0.2);
+        assert_eq!(body["model"], Model::Gpt5.to_api_str());
+    }
+
+    #[test]
+    fn test_extra_request_fields_cannot_replace_model() {
+        let options = ClientOptions {
+            extra_fields: serde_json::from_value(serde_json::json!({ "model": "other" })).unwrap(),
+            ..Default::default()
+        };
+        let client = Client::new(None, Model::Gpt5, &options);
+
+        assert!(client.build_body(&test_prompt()).is_err());
+    }
+
+    #[test]
+    fn test_structured_request_includes_schema() {
+        let client = Client::new(None, Model::Gpt5, &ClientOptions::default());
+        assert!(sample_request(&client).get("response_format").is_none());
+
+        let options = ClientOptions {
+            structured: true,
+            ..Default::default()
+        };
+        let client = Client::new(None, Model::Gpt5, &options);
+        let body = sample_request(&client);
+
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        let schema = &body["response_format"]["json_schema"]["schema"];
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["type", "scope", "description", "body"])
+        );
+        assert_eq!(schema["additionalProperties"], false);
+    }
+
+    #[test]
+    fn test_structured_response_assembles_message() {
+        let message = assemble_structured(
+            r#"{"type": "feat", "scope": "cli", "description": "add --structured", "body": "Uses a JSON schema."}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            "feat(cli): add --structured\n\nUses a JSON schema."
+        );
+
+        let message = assemble_structured(
+            r#"{"type": "fix", "scope": null, "description": "handle empty diff", "body": null}"#,
+        )
+        .unwrap();
+        assert_eq!(message, "fix: handle empty diff");
+
+        assert!(assemble_structured("feat: not json").is_err());
     }
 }
@@ -1,11 +1,15 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use crate::ai;
+use crate::project::CommitDefaults;
 
 #[derive(Parser)]
-#[command(name = "convmit")]
+#[command(name = "convmit", version)]
 #[command(about = "Generate conventional commit messages using Claude AI")]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(long, help = "Set the Claude API key in config")]
     pub set_claude_key: Option<String>,
 
@@ -18,14 +22,60 @@ pub struct Cli {
     #[arg(long, help = "Set the Mistral API key in config")]
     pub set_mistral_key: Option<String>,
 
-    #[arg(long, help = "Set the default model in config")]
+    #[arg(long, help = "Set the Cohere API key in config")]
+    pub set_cohere_key: Option<String>,
+
+    #[arg(long, value_parser = parse_model, help = "Set the default model in config")]
     pub set_default_model: Option<ai::Model>,
 
     #[arg(long, help = "List all available models")]
     pub list_models: bool,
 
-    #[arg(short, long, help = "Specify model to use")]
-    pub model: Option<ai::Model>,
+    #[arg(
+        long,
+        requires = "list_models",
+        help = "Ask each configured provider which models your API key can use"
+    )]
+    pub remote: bool,
+
+    #[arg(
+        long,
+        requires = "remote",
+        help = "Ignore the cached remote model lists and fetch them again"
+    )]
+    pub refresh_models: bool,
+
+    #[arg(
+        long,
+        value_name = "MODEL",
+        value_parser = parse_model,
+        help = "Show a model's provider, API id, context window and pricing"
+    )]
+    pub model_info: Option<ai::Model>,
+
+    #[arg(
+        short,
+        long,
+        value_parser = parse_model,
+        value_delimiter = ',',
+        help = "Specify model to use (see --list-models, or local:<name> for a llama.cpp server); a comma-separated list tries each in turn when a request fails"
+    )]
+    pub model: Vec<ai::Model>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Model id to send to an OpenAI-compatible endpoint instead of the selected model's"
+    )]
+    pub model_name: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "MODEL",
+        value_parser = parse_model,
+        help = "Model to fall back to when the main model keeps returning invalid messages"
+    )]
+    pub retry_model: Option<ai::Model>,
 
     #[arg(
         short,
@@ -35,6 +85,186 @@ pub struct Cli {
     )]
     pub no_commit: bool,
 
+    #[arg(short, long, help = "Print extra diagnostic information")]
+    pub verbose: bool,
+
+    #[arg(short, long, help = "Generate a body in addition to the subject line")]
+    pub body: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "body",
+        help = "Generate only a subject line, even if the project config enables bodies"
+    )]
+    pub no_body: bool,
+
+    #[arg(
+        long,
+        value_name = "WIDTH",
+        help = "Wrap the body at WIDTH columns (0 disables wrapping)"
+    )]
+    pub wrap: Option<usize>,
+
+    #[arg(
+        long,
+        conflicts_with = "no_body",
+        help = "Skip the body when the change touches fewer than 5 lines in a single file"
+    )]
+    pub no_body_if_trivial: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["no_body", "no_body_if_trivial"],
+        help = "Always generate a body, even for trivial changes"
+    )]
+    pub force_body: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Maximum output tokens (0 for uncapped, default 1024 or 2048 with --body)"
+    )]
+    pub max_tokens: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Amend HEAD using the combined diff of HEAD and staged changes"
+    )]
+    pub amend: bool,
+
+    #[arg(
+        long,
+        requires = "amend",
+        help = "Keep HEAD's subject and only regenerate the body when amending"
+    )]
+    pub keep_subject: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "amend",
+        help = "Summarize everything since the latest tag (never commits)"
+    )]
+    pub since_tag: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["amend", "no_commit"],
+        help = "Attach the generated message to HEAD as a git note instead of committing"
+    )]
+    pub as_note: bool,
+
+    #[arg(
+        long,
+        value_name = "DATE",
+        value_parser = parse_git_date,
+        help = "Override the commit date (any format git accepts)"
+    )]
+    pub date: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["amend", "as_note"],
+        help = "Allow an empty commit, generated from the branch, latest tag and recent commits"
+    )]
+    pub allow_empty: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Choose which staged hunks the message should describe"
+    )]
+    pub patch: bool,
+
+    #[arg(long, help = "Commit even when HEAD is detached")]
+    pub force: bool,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = ["amend", "as_note", "no_commit", "since_tag", "summary_only", "split", "validate_file"],
+        help = "Create and switch to a new branch before committing"
+    )]
+    pub branch: Option<String>,
+
+    #[arg(
+        long,
+        requires = "branch",
+        help = "Reset the --branch to HEAD if it already exists, dropping its own commits"
+    )]
+    pub reset_branch: bool,
+
+    #[arg(
+        long,
+        value_name = "CMD",
+        help = "Run a shell command after committing, with CONVMIT_SHA and CONVMIT_MESSAGE set"
+    )]
+    pub after_commit: Option<String>,
+
+    #[arg(long, help = "Fail the run when the post-commit command fails")]
+    pub strict: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["amend", "as_note", "since_tag"],
+        help = "Check the message in PATH is a valid conventional commit (for commit-msg hooks)"
+    )]
+    pub validate_file: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        requires = "validate_file",
+        help = "Regenerate an invalid message from the staged changes and rewrite the file"
+    )]
+    pub fix: bool,
+
+    #[arg(
+        long,
+        help = "Pick the scope from ones used in recent commits instead of the model's choice"
+    )]
+    pub interactive_scope: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["amend", "as_note", "since_tag", "allow_empty", "patch", "validate_file"],
+        help = "Group staged files by top-level directory and print a script committing each group"
+    )]
+    pub split: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["amend", "as_note", "split", "validate_file", "keep_subject"],
+        help = "Print a plain one-sentence summary of the staged changes (never commits)"
+    )]
+    pub summary_only: bool,
+
+    #[arg(
+        long,
+        requires = "split",
+        help = "Commit each --split group instead of printing the script"
+    )]
+    pub execute: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format; json prints a single outcome report for scripts"
+    )]
+    pub format: OutputFormat,
+
+    #[arg(
+        long,
+        help = "Exit 0 even when the run fails, leaving the decision to the caller"
+    )]
+    pub keep_going: bool,
+
+    #[arg(
+        long,
+        help = "Add a Generated-by trailer naming the model that wrote the message"
+    )]
+    pub attribution: bool,
+
     #[arg(long, help = "Edit the generated message before using it")]
     pub edit: bool,
 
@@ -59,7 +289,413 @@ pub struct Cli {
     #[arg(
         short,
         long,
-        help = "Additional context to help generate the commit message"
+        help = "Additional context to help generate the commit message (repeatable)"
+    )]
+    pub context: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "CMD",
+        help = "Run a shell command and add its output to the prompt as context"
+    )]
+    pub context_command: Option<String>,
+
+    #[arg(
+        long,
+        help = "Send whole changed functions instead of minimal hunks (git diff -W)"
+    )]
+    pub function_context: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "50",
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        help = "Detect renames with at least N% similarity (git diff -M, default 50)"
+    )]
+    pub find_renames: Option<u8>,
+
+    #[arg(
+        long,
+        conflicts_with = "patch",
+        help = "Diff each file separately and label it with a === path === separator"
+    )]
+    pub labeled_diff: bool,
+
+    #[arg(long, help = "Detect copies as well as renames (git diff -C)")]
+    pub find_copies: bool,
+
+    #[arg(
+        long,
+        help = "Prefix each staged file with its language, e.g. [rust] src/ai.rs"
+    )]
+    pub language_tags: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "stat_only",
+        help = "Leave the staged file list out of the prompt when the full diff is sent"
+    )]
+    pub trim_prompt_files: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "stat_only",
+        help = "Keep only the first N diff lines of each changed file"
+    )]
+    pub head_lines: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Pick the commit type from file roles when all staged files agree (docs, ci, test, build)"
+    )]
+    pub infer_type: bool,
+
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = true,
+        default_missing_value = "true",
+        action = clap::ArgAction::Set,
+        help = "Replace likely secrets in the diff with [REDACTED] before sending (use =false to disable)"
+    )]
+    pub redact_secrets: bool,
+
+    #[arg(
+        long,
+        help = "Print the diff to stderr exactly as it will be sent, after filtering and redaction"
+    )]
+    pub print_diff_sent: bool,
+
+    #[arg(long, help = "Include `git diff --stat` output in the prompt")]
+    pub stat_context: bool,
+
+    #[arg(
+        long,
+        help = "Send only the diff stat instead of the full diff (implies --stat-context)"
+    )]
+    pub stat_only: bool,
+
+    #[arg(
+        long,
+        help = "Extra instruction appended to the system prompt for this run"
+    )]
+    pub instruction: Option<String>,
+
+    #[arg(
+        long,
+        help = "Request JSON structured output and assemble the message locally (OpenAI only)"
+    )]
+    pub structured: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "structured",
+        help = "Ask the model why it chose the commit type and print the answer to stderr"
+    )]
+    pub explain_type: bool,
+
+    #[arg(
+        long,
+        help = "Check the generated message with commitlint before committing"
+    )]
+    pub commitlint: bool,
+
+    #[arg(
+        long,
+        help = "Fail any request to a model provider instead of sending it, for offline CI checks"
+    )]
+    pub no_network: bool,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Give up on a provider request after this many seconds, 0 waits forever (default: 60)"
+    )]
+    pub timeout: Option<u64>,
+
+    #[arg(
+        long,
+        conflicts_with = "no_body",
+        help = "Shape the body after the repository's pull request template"
+    )]
+    pub pr_template: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["summary_only", "keep_subject", "structured", "split"],
+        help = "Ask for the type and scope first, then for a message within that classification (sends the diff twice)"
     )]
-    pub context: Option<String>,
+    pub two_phase: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(1..=10),
+        conflicts_with_all = ["summary_only", "split", "two_phase", "validate_file", "retry_model"],
+        help = "Generate N candidate messages and pick one before committing"
+    )]
+    pub count: Option<u8>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SOURCE",
+        help = "Show recent commit subjects as style examples, from the whole repo or only your own"
+    )]
+    pub learn_style: Option<StyleSource>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["count", "two_phase", "split", "structured"],
+        help = "Print the reply to stderr as it streams in (OpenAI, Mistral and Claude)"
+    )]
+    pub stream: bool,
+
+    #[arg(
+        long,
+        help = "Add an estimated cost to the token usage line, from the model's list price"
+    )]
+    pub show_cost: bool,
+
+    #[arg(
+        long,
+        value_name = "TOKENS",
+        help = "Truncate the diff to roughly this many tokens (4 characters each) before sending it"
+    )]
+    pub max_diff_tokens: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Print each request body to stderr, with the diff summarized and the API key masked"
+    )]
+    pub log_request: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StyleSource {
+    Repo,
+    Mine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Manage the convmit config")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    #[command(about = "Print version information")]
+    Version {
+        #[arg(
+            short,
+            long,
+            help = "Include the build commit, config path and compiled-in providers"
+        )]
+        verbose: bool,
+    },
+
+    #[command(
+        about = "Check a commit message against the conventional commit rules, no model needed"
+    )]
+    Lint {
+        #[arg(help = "The commit message, or a path to a file containing it")]
+        input: String,
+    },
+
+    #[command(about = "List scopes used in recent commits, most frequent first")]
+    Scopes {
+        #[arg(long, default_value_t = 500, help = "Number of recent commits to scan")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    #[command(about = "Remove all stored API keys from the config")]
+    Reset {
+        #[arg(long, help = "Skip the confirmation prompt")]
+        force: bool,
+    },
+
+    #[command(
+        about = "Show where each setting's active value comes from: CLI, project config, config file or env"
+    )]
+    Sources,
+}
+
+impl Cli {
+    pub fn should_commit(&self) -> bool {
+        !self.no_commit && !self.since_tag && !self.summary_only
+    }
+
+    pub fn commit_overrides(&self) -> CommitDefaults {
+        let body = if self.body || self.keep_subject || self.force_body || self.pr_template {
+            Some(true)
+        } else if self.no_body {
+            Some(false)
+        } else {
+            None
+        };
+
+        let no_body_if_trivial = if self.force_body || self.keep_subject {
+            Some(false)
+        } else if self.no_body_if_trivial {
+            Some(true)
+        } else {
+            None
+        };
+
+        CommitDefaults {
+            body,
+            wrap: self.wrap,
+            no_body_if_trivial,
+            ..Default::default()
+        }
+    }
+}
+
+fn parse_model(arg: &str) -> anyhow::Result<ai::Model> {
+    arg.parse()
+}
+
+// git does the real parsing, this only rejects values that can't be a date
+fn parse_git_date(arg: &str) -> anyhow::Result<String> {
+    let date = arg.trim();
+    if date.is_empty() {
+        return Err(anyhow::anyhow!("Date cannot be empty"));
+    }
+    if date.starts_with('-') || date.contains(['\n', '\r']) {
+        return Err(anyhow::anyhow!("Invalid date \"{arg}\""));
+    }
+    Ok(date.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_overrides_from_flags() {
+        let cli = Cli::parse_from(["convmit"]);
+        assert_eq!(cli.commit_overrides(), CommitDefaults::default());
+
+        let cli = Cli::parse_from(["convmit", "--body", "--wrap", "72"]);
+        let overrides = cli.commit_overrides();
+        assert_eq!(overrides.body, Some(true));
+        assert_eq!(overrides.wrap, Some(72));
+
+        let cli = Cli::parse_from(["convmit", "--no-body"]);
+        assert_eq!(cli.commit_overrides().body, Some(false));
+
+        let cli = Cli::parse_from(["convmit", "--force-body"]);
+        let overrides = cli.commit_overrides();
+        assert_eq!(overrides.body, Some(true));
+        assert_eq!(overrides.no_body_if_trivial, Some(false));
+    }
+
+    #[test]
+    fn test_reset_branch_is_separate_from_force() {
+        let cli = Cli::parse_from(["convmit", "--branch", "feat/x", "--force"]);
+        assert!(cli.force);
+        assert!(!cli.reset_branch);
+
+        let cli = Cli::parse_from(["convmit", "--branch", "feat/x", "--reset-branch"]);
+        assert!(cli.reset_branch);
+        assert!(!cli.force);
+
+        assert!(Cli::try_parse_from(["convmit", "--reset-branch"]).is_err());
+    }
+
+    #[test]
+    fn test_summary_only_never_commits() {
+        assert!(Cli::parse_from(["convmit"]).should_commit());
+        assert!(!Cli::parse_from(["convmit", "--summary-only"]).should_commit());
+        assert!(Cli::try_parse_from(["convmit", "--summary-only", "--amend"]).is_err());
+    }
+
+    #[test]
+    fn test_count_rejects_retry_model() {
+        assert!(Cli::try_parse_from(["convmit", "--count", "3"]).is_ok());
+        assert!(
+            Cli::try_parse_from(["convmit", "--count", "3", "--retry-model", "sonnet4"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_context_is_repeatable() {
+        let cli = Cli::parse_from([
+            "convmit",
+            "-c",
+            "Fixes PROJ-42",
+            "--context",
+            "See design.md",
+        ]);
+        assert_eq!(cli.context, vec!["Fixes PROJ-42", "See design.md"]);
+    }
+
+    #[test]
+    fn test_config_reset_subcommand() {
+        let cli = Cli::parse_from(["convmit", "config", "reset", "--force"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Config {
+                action: ConfigAction::Reset { force: true }
+            })
+        ));
+
+        assert!(Cli::parse_from(["convmit"]).command.is_none());
+    }
+
+    #[test]
+    fn test_date_is_validated_loosely() {
+        let cli = Cli::parse_from(["convmit", "--date", " 2024-03-01T12:00:00 "]);
+        assert_eq!(cli.date.as_deref(), Some("2024-03-01T12:00:00"));
+
+        assert!(Cli::try_parse_from(["convmit", "--date", ""]).is_err());
+        assert!(Cli::try_parse_from(["convmit", "--date=--amend"]).is_err());
+    }
+
+    #[test]
+    fn test_redact_secrets_defaults_on() {
+        assert!(Cli::parse_from(["convmit"]).redact_secrets);
+        assert!(Cli::parse_from(["convmit", "--redact-secrets"]).redact_secrets);
+        assert!(!Cli::parse_from(["convmit", "--redact-secrets=false"]).redact_secrets);
+    }
+
+    #[test]
+    fn test_model_chain_keeps_order() {
+        assert!(Cli::parse_from(["convmit"]).model.is_empty());
+
+        let cli = Cli::parse_from(["convmit", "-m", "sonnet4,gpt5-mini"]);
+        assert_eq!(cli.model, vec![ai::Model::Sonnet4, ai::Model::Gpt5Mini]);
+
+        assert!(Cli::try_parse_from(["convmit", "-m", "sonnet4,not-a-model"]).is_err());
+    }
+
+    #[test]
+    fn test_find_renames_threshold() {
+        assert_eq!(Cli::parse_from(["convmit"]).find_renames, None);
+        assert_eq!(
+            Cli::parse_from(["convmit", "--find-renames"]).find_renames,
+            Some(50)
+        );
+        assert_eq!(
+            Cli::parse_from(["convmit", "--find-renames=80"]).find_renames,
+            Some(80)
+        );
+        assert!(Cli::try_parse_from(["convmit", "--find-renames=120"]).is_err());
+    }
 }
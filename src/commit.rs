@@ -0,0 +1,826 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+    pub body: Option<String>,
+    pub footers: Vec<(String, String)>,
+}
+
+impl ConventionalCommit {
+    pub fn parse(message: &str) -> anyhow::Result<Self> {
+        let message = message.trim();
+        let mut lines = message.lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Commit message is empty"))?
+            .trim();
+
+        let (prefix, description) = header
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Missing ':' after the commit type in \"{header}\""))?;
+
+        let description = description.trim();
+        if description.is_empty() {
+            return Err(anyhow::anyhow!("Commit description is empty"));
+        }
+
+        let (prefix, header_breaking) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+
+        let (commit_type, scope) = match prefix.split_once('(') {
+            Some((commit_type, rest)) => {
+                let scope = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow::anyhow!("Unclosed scope in \"{header}\""))?;
+                if scope.trim().is_empty() {
+                    return Err(anyhow::anyhow!("Scope is empty in \"{header}\""));
+                }
+                (commit_type, Some(scope.trim().to_string()))
+            }
+            None => (prefix, None),
+        };
+
+        if commit_type.is_empty()
+            || !commit_type
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(anyhow::anyhow!("Invalid commit type \"{commit_type}\""));
+        }
+
+        let rest: Vec<&str> = lines.collect();
+        let mut paragraphs = split_paragraphs(&rest);
+
+        let footers = match paragraphs.last() {
+            Some(last)
+                if last
+                    .first()
+                    .is_some_and(|line| parse_footer(line).is_some()) =>
+            {
+                let last = paragraphs.pop().unwrap_or_default();
+                parse_footers(&last)
+            }
+            _ => Vec::new(),
+        };
+
+        let body = paragraphs
+            .iter()
+            .map(|paragraph| paragraph.join("\n"))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let breaking = header_breaking
+            || footers
+                .iter()
+                .any(|(token, _)| is_breaking_change_token(token));
+
+        Ok(Self {
+            commit_type: commit_type.to_string(),
+            scope,
+            breaking,
+            description: description.to_string(),
+            body: (!body.is_empty()).then_some(body),
+            footers,
+        })
+    }
+
+    pub fn normalize_scope(&mut self) -> anyhow::Result<()> {
+        if let Some(scope) = &self.scope {
+            self.scope = Some(normalize_scope(scope)?);
+        }
+        Ok(())
+    }
+
+    pub fn subject(&self) -> String {
+        let scope = self
+            .scope
+            .as_ref()
+            .map(|scope| format!("({scope})"))
+            .unwrap_or_default();
+        let has_breaking_footer = self
+            .footers
+            .iter()
+            .any(|(token, _)| is_breaking_change_token(token));
+        let marker = if self.breaking && !has_breaking_footer {
+            "!"
+        } else {
+            ""
+        };
+
+        format!(
+            "{}{}{}: {}",
+            self.commit_type, scope, marker, self.description
+        )
+    }
+}
+
+impl Display for ConventionalCommit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.subject())?;
+
+        if let Some(body) = &self.body {
+            write!(f, "\n\n{body}")?;
+        }
+
+        if !self.footers.is_empty() {
+            writeln!(f)?;
+            for (token, value) in &self.footers {
+                if value.starts_with('#') {
+                    write!(f, "\n{token} {value}")?;
+                } else {
+                    write!(f, "\n{token}: {value}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn normalize_scope(scope: &str) -> anyhow::Result<String> {
+    let normalized = scope
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+
+    if let Some(invalid) = normalized
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | '.')))
+    {
+        return Err(anyhow::anyhow!(
+            "Scope \"{scope}\" contains invalid character '{invalid}'"
+        ));
+    }
+
+    Ok(normalized)
+}
+
+pub fn normalize_message_scope(message: &str) -> anyhow::Result<String> {
+    match ConventionalCommit::parse(message) {
+        Ok(mut commit) => {
+            commit.normalize_scope()?;
+            Ok(commit.to_string())
+        }
+        Err(_) => Ok(message.to_string()),
+    }
+}
+
+const SCISSORS_MARKER: &str = ">8";
+
+pub const SCISSORS_LINE: &str = "# ------------------------ >8 ------------------------";
+
+const SCISSORS_HELP: &str =
+    "# Do not modify or remove the line above.\n# Everything below it will be ignored.";
+
+// Same layout `git commit --cleanup=scissors` reads, with the context below the cut
+pub fn add_scissors(message: &str, context: &str) -> String {
+    format!(
+        "{}\n\n{SCISSORS_LINE}\n{SCISSORS_HELP}\n{}",
+        message.trim_end(),
+        context.trim_end()
+    )
+}
+
+// Unlike strip_comments, "#" lines above the cut are kept, only the exact scissors line cuts
+pub fn strip_scissors(message: &str) -> String {
+    message
+        .lines()
+        .take_while(|line| *line != SCISSORS_LINE)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
+
+// Drops the "# Please enter the commit message..." help text git adds to the message file,
+// and everything below the scissors line `git commit -v` adds
+pub fn strip_comments(message: &str) -> String {
+    message
+        .lines()
+        .take_while(|line| !(line.starts_with("# ") && line.contains(SCISSORS_MARKER)))
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+// The conventional commit types, plus the "merge" type the merge prompt asks for
+pub const KNOWN_TYPES: [&str; 12] = [
+    "feat", "fix", "docs", "style", "refactor", "test", "chore", "perf", "ci", "build", "revert",
+    "merge",
+];
+
+// Subjects git writes itself for merges, reverts and `git commit --fixup/--squash`
+const GIT_GENERATED_PREFIXES: &[&str] = &[
+    "Merge branch ",
+    "Merge remote-tracking branch ",
+    "Merge pull request ",
+    "Merge tag ",
+    "Merge commit ",
+    "Revert \"",
+    "fixup! ",
+    "squash! ",
+    "amend! ",
+];
+
+pub fn is_git_generated(message: &str) -> bool {
+    let subject = message.trim_start().lines().next().unwrap_or_default();
+    GIT_GENERATED_PREFIXES
+        .iter()
+        .any(|prefix| subject.starts_with(prefix))
+}
+
+pub fn force_type(message: &str, commit_type: &str) -> String {
+    match ConventionalCommit::parse(message) {
+        Ok(mut commit) => {
+            commit.commit_type = commit_type.to_string();
+            commit.to_string()
+        }
+        Err(_) => message.to_string(),
+    }
+}
+
+pub fn force_scope(message: &str, scope: Option<&str>) -> String {
+    match ConventionalCommit::parse(message) {
+        Ok(mut commit) => {
+            commit.scope = scope.map(str::to_string);
+            commit.to_string()
+        }
+        Err(_) => message.to_string(),
+    }
+}
+
+pub fn scope_frequencies(subjects: &[String]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for scope in subjects
+        .iter()
+        .filter_map(|subject| ConventionalCommit::parse(subject).ok()?.scope)
+    {
+        *counts.entry(scope).or_default() += 1;
+    }
+
+    let mut frequencies: Vec<(String, usize)> = counts.into_iter().collect();
+    frequencies.sort_by(|(a_scope, a_count), (b_scope, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_scope.cmp(b_scope))
+    });
+    frequencies
+}
+
+pub const ATTRIBUTION_TOKEN: &str = "Generated-by";
+
+pub fn attribution_value(api_id: &str) -> String {
+    format!("convmit ({api_id})")
+}
+
+// Joins an existing trailer block instead of starting a new paragraph, and never duplicates
+pub fn append_trailer(message: &str, token: &str, value: &str) -> String {
+    let message = message.trim_end();
+    let trailer = format!("{token}: {value}");
+
+    let rest: Vec<&str> = message.lines().skip(1).collect();
+    let paragraphs = split_paragraphs(&rest);
+    let footers = paragraphs.last().filter(|last| {
+        last.first()
+            .is_some_and(|line| parse_footer(line).is_some())
+    });
+
+    match footers {
+        Some(footers) if footers.iter().any(|line| line.trim() == trailer) => message.to_string(),
+        Some(_) => format!("{message}\n{trailer}"),
+        None => format!("{message}\n\n{trailer}"),
+    }
+}
+
+pub fn splice_body(old_message: &str, new_body: &str) -> String {
+    let mut lines = old_message.trim().lines();
+    let subject = lines.next().unwrap_or_default().trim_end();
+    let rest: Vec<&str> = lines.collect();
+    let paragraphs = split_paragraphs(&rest);
+
+    let footers = paragraphs
+        .last()
+        .filter(|last| {
+            last.first()
+                .is_some_and(|line| parse_footer(line).is_some())
+        })
+        .map(|last| last.join("\n"));
+
+    let mut message = subject.to_string();
+    let new_body = new_body.trim();
+    if !new_body.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(new_body);
+    }
+    if let Some(footers) = footers {
+        message.push_str("\n\n");
+        message.push_str(&footers);
+    }
+
+    message
+}
+
+pub fn wrap_message_body(message: &str, width: usize) -> String {
+    match ConventionalCommit::parse(message) {
+        Ok(mut commit) => {
+            commit.body = commit.body.map(|body| wrap_text(&body, width));
+            commit.to_string()
+        }
+        Err(_) => message.to_string(),
+    }
+}
+
+pub fn wrap_text(text: &str, width: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    split_paragraphs(&lines)
+        .iter()
+        .map(|paragraph| wrap_paragraph(paragraph, width))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn wrap_paragraph(lines: &[&str], width: usize) -> String {
+    // Each bullet starts a new item so lists keep their shape after reflowing
+    let mut items: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in lines {
+        let trimmed = line.trim();
+        let bullet = ["- ", "* "]
+            .into_iter()
+            .find(|bullet| trimmed.starts_with(bullet));
+
+        match (bullet, items.last_mut()) {
+            (Some(bullet), _) => {
+                items.push((bullet, trimmed[bullet.len()..].split_whitespace().collect()))
+            }
+            (None, Some((_, words))) => words.extend(trimmed.split_whitespace()),
+            (None, None) => items.push(("", trimmed.split_whitespace().collect())),
+        }
+    }
+
+    items
+        .iter()
+        .map(|(bullet, words)| wrap_words(bullet, words, width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn wrap_words(bullet: &str, words: &[&str], width: usize) -> String {
+    let indent = " ".repeat(bullet.len());
+    let mut lines = Vec::new();
+    let mut current = bullet.to_string();
+
+    for word in words {
+        let line_start = current.len() == bullet.len();
+        if !line_start && current.len() + 1 + word.len() > width {
+            lines.push(std::mem::replace(&mut current, indent.clone()));
+        }
+        if current.len() > bullet.len() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+
+    lines.push(current);
+    lines.join("\n")
+}
+
+fn split_paragraphs<'a>(lines: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut paragraphs = Vec::new();
+    let mut current = Vec::new();
+
+    for line in lines {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line);
+        }
+    }
+
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+
+    paragraphs
+}
+
+fn is_breaking_change_token(token: &str) -> bool {
+    token == "BREAKING CHANGE" || token == "BREAKING-CHANGE"
+}
+
+pub fn is_footer_line(line: &str) -> bool {
+    parse_footer(line).is_some()
+}
+
+fn parse_footer(line: &str) -> Option<(String, String)> {
+    for breaking in ["BREAKING CHANGE", "BREAKING-CHANGE"] {
+        if let Some(value) = line
+            .strip_prefix(breaking)
+            .and_then(|rest| rest.strip_prefix(": "))
+        {
+            return Some((breaking.to_string(), value.trim().to_string()));
+        }
+    }
+
+    let token_end = line.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))?;
+    let (token, rest) = line.split_at(token_end);
+    if token.is_empty() {
+        return None;
+    }
+
+    if let Some(value) = rest.strip_prefix(": ") {
+        Some((token.to_string(), value.trim().to_string()))
+    } else {
+        rest.strip_prefix(" #")
+            .map(|value| (token.to_string(), format!("#{}", value.trim())))
+    }
+}
+
+fn parse_footers(lines: &[&str]) -> Vec<(String, String)> {
+    let mut footers: Vec<(String, String)> = Vec::new();
+
+    for line in lines {
+        match parse_footer(line) {
+            Some(footer) => footers.push(footer),
+            None => {
+                if let Some((_, value)) = footers.last_mut() {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+        }
+    }
+
+    footers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subject_only() {
+        let commit = ConventionalCommit::parse("feat: add OAuth2 login support").unwrap();
+
+        assert_eq!(commit.commit_type, "feat");
+        assert_eq!(commit.scope, None);
+        assert!(!commit.breaking);
+        assert_eq!(commit.description, "add OAuth2 login support");
+        assert_eq!(commit.body, None);
+        assert!(commit.footers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_scope() {
+        let commit = ConventionalCommit::parse("fix(parser): handle empty input").unwrap();
+
+        assert_eq!(commit.commit_type, "fix");
+        assert_eq!(commit.scope.as_deref(), Some("parser"));
+        assert_eq!(commit.description, "handle empty input");
+    }
+
+    #[test]
+    fn test_parse_breaking_marker() {
+        let commit = ConventionalCommit::parse("refactor(api)!: drop v1 endpoints").unwrap();
+
+        assert!(commit.breaking);
+        assert_eq!(commit.scope.as_deref(), Some("api"));
+
+        let commit = ConventionalCommit::parse("feat!: require config file").unwrap();
+        assert!(commit.breaking);
+        assert_eq!(commit.scope, None);
+    }
+
+    #[test]
+    fn test_parse_breaking_footer() {
+        let message =
+            "feat(config): rename default_model\n\nBREAKING CHANGE: default_model is now model";
+        let commit = ConventionalCommit::parse(message).unwrap();
+
+        assert!(commit.breaking);
+        assert_eq!(commit.body, None);
+        assert_eq!(
+            commit.footers,
+            vec![(
+                "BREAKING CHANGE".to_string(),
+                "default_model is now model".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_multi_paragraph_body_and_footers() {
+        let message = "fix(git): read diffs lossily
+
+Some repositories contain latin-1 files which made
+the whole diff fail to decode.
+
+Fall back to lossy decoding instead.
+
+Reviewed-by: Alice
+Refs #123";
+
+        let commit = ConventionalCommit::parse(message).unwrap();
+
+        assert_eq!(
+            commit.body.as_deref(),
+            Some(
+                "Some repositories contain latin-1 files which made\nthe whole diff fail to decode.\n\nFall back to lossy decoding instead."
+            )
+        );
+        assert_eq!(
+            commit.footers,
+            vec![
+                ("Reviewed-by".to_string(), "Alice".to_string()),
+                ("Refs".to_string(), "#123".to_string()),
+            ]
+        );
+        assert!(!commit.breaking);
+    }
+
+    #[test]
+    fn test_parse_footer_continuation_lines() {
+        let message = "feat: switch storage\n\nBREAKING-CHANGE: data must be migrated\n  run `convmit migrate` first";
+        let commit = ConventionalCommit::parse(message).unwrap();
+
+        assert!(commit.breaking);
+        assert_eq!(
+            commit.footers[0].1,
+            "data must be migrated\n  run `convmit migrate` first"
+        );
+    }
+
+    #[test]
+    fn test_parse_body_without_footers() {
+        let message = "docs: explain config\n\nThis paragraph is the body.\nIt spans lines.";
+        let commit = ConventionalCommit::parse(message).unwrap();
+
+        assert_eq!(
+            commit.body.as_deref(),
+            Some("This paragraph is the body.\nIt spans lines.")
+        );
+        assert!(commit.footers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_headers() {
+        assert!(ConventionalCommit::parse("").is_err());
+        assert!(ConventionalCommit::parse("add a thing").is_err());
+        assert!(ConventionalCommit::parse("feat:").is_err());
+        assert!(ConventionalCommit::parse(": missing type").is_err());
+        assert!(ConventionalCommit::parse("feat(scope: unclosed").is_err());
+        assert!(ConventionalCommit::parse("feat(): empty scope").is_err());
+        assert!(ConventionalCommit::parse("new feature: spaces in type").is_err());
+    }
+
+    #[test]
+    fn test_normalize_scope() {
+        assert_eq!(normalize_scope("Auth Module").unwrap(), "auth-module");
+        assert_eq!(normalize_scope("  API ").unwrap(), "api");
+        assert_eq!(
+            normalize_scope("src/ai/claude.rs").unwrap(),
+            "src/ai/claude.rs"
+        );
+        assert_eq!(normalize_scope("Web  UI").unwrap(), "web-ui");
+    }
+
+    #[test]
+    fn test_normalize_scope_rejects_invalid_characters() {
+        let error = normalize_scope("auth+db").unwrap_err().to_string();
+        assert!(error.contains("invalid character '+'"));
+
+        assert!(normalize_scope("core,cli").is_err());
+        assert!(normalize_scope("ui_kit").is_err());
+    }
+
+    #[test]
+    fn test_normalize_message_scope() {
+        assert_eq!(
+            normalize_message_scope("feat(Auth Module): add login\n\nBody text.").unwrap(),
+            "feat(auth-module): add login\n\nBody text."
+        );
+        assert_eq!(
+            normalize_message_scope("fix: no scope here").unwrap(),
+            "fix: no scope here"
+        );
+        assert_eq!(
+            normalize_message_scope("Not conventional").unwrap(),
+            "Not conventional"
+        );
+        assert!(normalize_message_scope("feat(a&b): add").is_err());
+    }
+
+    #[test]
+    fn test_splice_body_keeps_subject() {
+        let old = "feat(auth): add OAuth2 login support\n\nInitial body.";
+
+        assert_eq!(
+            splice_body(old, "Adds PKCE support.\n\nAlso refreshes tokens."),
+            "feat(auth): add OAuth2 login support\n\nAdds PKCE support.\n\nAlso refreshes tokens."
+        );
+    }
+
+    #[test]
+    fn test_splice_body_keeps_footers() {
+        let old =
+            "fix: handle empty input\n\nOld body.\n\nSigned-off-by: Alice <alice@example.com>";
+
+        assert_eq!(
+            splice_body(old, "New body.\n"),
+            "fix: handle empty input\n\nNew body.\n\nSigned-off-by: Alice <alice@example.com>"
+        );
+    }
+
+    #[test]
+    fn test_splice_body_into_subject_only_message() {
+        assert_eq!(
+            splice_body("Update things", "Explain the update."),
+            "Update things\n\nExplain the update."
+        );
+        assert_eq!(splice_body("Update things", "  "), "Update things");
+    }
+
+    #[test]
+    fn test_force_type() {
+        assert_eq!(
+            force_type("chore(readme): fix typo\n\nSpelling only.", "docs"),
+            "docs(readme): fix typo\n\nSpelling only."
+        );
+        assert_eq!(force_type("Fix typo", "docs"), "Fix typo");
+    }
+
+    #[test]
+    fn test_append_trailer() {
+        assert_eq!(
+            append_trailer("feat: add thing", "Refs", "#12"),
+            "feat: add thing\n\nRefs: #12"
+        );
+        assert_eq!(
+            append_trailer(
+                "feat: add thing\n\nSome body.\n\nRefs: #12\n",
+                "Signed-off-by",
+                "Jo <jo@example.com>"
+            ),
+            "feat: add thing\n\nSome body.\n\nRefs: #12\nSigned-off-by: Jo <jo@example.com>"
+        );
+
+        let message = "fix: handle empty input\n\nRefs: #12\nGenerated-by: convmit (x)";
+        assert_eq!(
+            append_trailer(message, "Generated-by", "convmit (x)"),
+            message
+        );
+    }
+
+    #[test]
+    fn test_attribution_trailer_uses_api_id() {
+        let model = crate::ai::Model::Sonnet4;
+
+        let message = append_trailer(
+            "feat: add thing",
+            ATTRIBUTION_TOKEN,
+            &attribution_value(&model.to_api_str()),
+        );
+
+        assert_eq!(
+            message,
+            format!(
+                "feat: add thing\n\nGenerated-by: convmit ({})",
+                model.to_api_str()
+            )
+        );
+        assert!(message.ends_with("(claude-sonnet-4-20250514)"));
+    }
+
+    #[test]
+    fn test_force_scope() {
+        assert_eq!(
+            force_scope("feat(api): add pagination\n\nPages of 50.", Some("server")),
+            "feat(server): add pagination\n\nPages of 50."
+        );
+        assert_eq!(
+            force_scope("fix!: drop legacy flag", Some("cli")),
+            "fix(cli)!: drop legacy flag"
+        );
+        assert_eq!(
+            force_scope("feat(api): add pagination", None),
+            "feat: add pagination"
+        );
+        assert_eq!(force_scope("Add pagination", Some("api")), "Add pagination");
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let message = "feat(cli): add --validate-file\n\nChecks hook messages.\n\n# Please enter the commit message for your changes.\n# ------------------------ >8 ------------------------\ndiff --git a/src/cli.rs b/src/cli.rs\n";
+
+        assert_eq!(
+            strip_comments(message),
+            "feat(cli): add --validate-file\n\nChecks hook messages."
+        );
+        assert_eq!(strip_comments("# only comments\n"), "");
+    }
+
+    #[test]
+    fn test_strip_scissors() {
+        let edited = add_scissors(
+            "feat(cli): add --count\n\n# Issue #42 stays\n",
+            "Staged files:\n  src/cli.rs",
+        );
+        assert!(edited.contains(SCISSORS_LINE));
+        assert!(edited.ends_with("src/cli.rs"));
+        assert_eq!(
+            strip_scissors(&edited),
+            "feat(cli): add --count\n\n# Issue #42 stays"
+        );
+
+        let edited = format!("fix: handle empty input\n{SCISSORS_LINE}\n\nfeat: something else\n");
+        assert_eq!(strip_scissors(&edited), "fix: handle empty input");
+        assert_eq!(
+            strip_scissors("fix: handle empty input\n\n"),
+            "fix: handle empty input"
+        );
+    }
+
+    #[test]
+    fn test_scope_frequencies() {
+        let subjects: Vec<String> = [
+            "feat(cli): add --date",
+            "fix(git): handle detached HEAD",
+            "feat(cli): add --as-note",
+            "docs: update readme",
+            "Merge branch 'main'",
+            "refactor(ai)!: split providers",
+            "fix(git): decode non-UTF-8 diffs",
+            "test(cli): cover subcommands",
+        ]
+        .iter()
+        .map(|subject| subject.to_string())
+        .collect();
+
+        assert_eq!(
+            scope_frequencies(&subjects),
+            vec![
+                ("cli".to_string(), 3),
+                ("git".to_string(), 2),
+                ("ai".to_string(), 1),
+            ]
+        );
+        assert!(scope_frequencies(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let messages = [
+            "feat: add OAuth2 login support",
+            "fix(parser)!: handle empty input",
+            "chore(deps): update reqwest\n\nBumps to the latest patch release.",
+            "feat(api): add endpoint\n\nFirst paragraph.\n\nSecond paragraph.\n\nRefs #42\nReviewed-by: Bob",
+            "feat: rename option\n\nBREAKING CHANGE: old option removed",
+        ];
+
+        for message in messages {
+            let commit = ConventionalCommit::parse(message).unwrap();
+            assert_eq!(commit.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_wrap_text_reflows_paragraphs() {
+        let text = "Adds a cache in front of the provider lookup so repeated runs do not hit the network.\n\nShort.";
+
+        assert_eq!(
+            wrap_text(text, 40),
+            "Adds a cache in front of the provider\nlookup so repeated runs do not hit the\nnetwork.\n\nShort."
+        );
+    }
+
+    #[test]
+    fn test_wrap_text_keeps_bullets() {
+        let text = "- first item that is long enough to need wrapping\n- second";
+
+        assert_eq!(
+            wrap_text(text, 24),
+            "- first item that is\n  long enough to need\n  wrapping\n- second"
+        );
+    }
+
+    #[test]
+    fn test_wrap_message_body_leaves_subject_and_footers() {
+        let message = "feat(api): add a very long subject line that should never be wrapped\n\nThe body is wrapped at the configured width.\n\nReviewed-by: Someone With A Long Name";
+
+        assert_eq!(
+            wrap_message_body(message, 20),
+            "feat(api): add a very long subject line that should never be wrapped\n\nThe body is wrapped\nat the configured\nwidth.\n\nReviewed-by: Someone With A Long Name"
+        );
+        assert_eq!(wrap_message_body("Update things", 10), "Update things");
+    }
+}
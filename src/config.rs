@@ -1,7 +1,36 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::ai::{DescriptionStyle, ExtraFields, HttpVersion};
+use crate::diff::TruncationStrategy;
+use crate::git::CommitMethod;
+
+// Where a setting's active value came from, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Cli,
+    Project,
+    Config,
+    Env,
+    Default,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Cli => write!(f, "command line"),
+            Source::Project => write!(f, "project config"),
+            Source::Config => write!(f, "config file"),
+            Source::Env => write!(f, "environment variable"),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -9,7 +38,42 @@ pub struct Config {
     pub openai_api_key: Option<String>,
     pub gemini_api_key: Option<String>,
     pub mistral_api_key: Option<String>,
+    pub cohere_api_key: Option<String>,
     pub default_model: Option<crate::ai::Model>,
+    pub default_model_by_host: Option<BTreeMap<String, crate::ai::Model>>,
+    pub llamacpp_base_url: Option<String>,
+    pub openai_base_url: Option<String>,
+    pub gemini_base_url: Option<String>,
+    pub pre_commit_validate_command: Option<String>,
+    pub post_commit_command: Option<String>,
+    pub normalize_scope: Option<bool>,
+    pub attribution: Option<bool>,
+    pub description_style: Option<DescriptionStyle>,
+    pub commit_method: Option<CommitMethod>,
+    pub allow_env_keys: Option<bool>,
+    pub load_dotenv: Option<bool>,
+    pub extra_request_fields: Option<ExtraFields>,
+    pub short_response_retries: Option<u32>,
+    pub retry_model: Option<crate::ai::Model>,
+    pub max_concurrency: Option<usize>,
+    pub diff_truncation: Option<TruncationStrategy>,
+    pub hard_max_diff_bytes: Option<usize>,
+    pub model_cache_ttl_hours: Option<u64>,
+    pub http_version: Option<HttpVersion>,
+    pub request_timeout_secs: Option<u64>,
+    pub edit_scissors: Option<bool>,
+    pub max_diff_tokens: Option<usize>,
+}
+
+// The `hostname` command exists on Linux, macOS and Windows alike
+fn current_hostname() -> Option<String> {
+    let output = Command::new("hostname").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let hostname = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!hostname.is_empty()).then_some(hostname)
 }
 
 impl Default for Config {
@@ -19,13 +83,37 @@ impl Default for Config {
             openai_api_key: None,
             gemini_api_key: None,
             mistral_api_key: None,
+            cohere_api_key: None,
             default_model: Some(crate::ai::Model::Haiku4_5),
+            default_model_by_host: None,
+            llamacpp_base_url: None,
+            openai_base_url: None,
+            gemini_base_url: None,
+            pre_commit_validate_command: None,
+            post_commit_command: None,
+            normalize_scope: None,
+            attribution: None,
+            description_style: None,
+            commit_method: None,
+            allow_env_keys: None,
+            load_dotenv: None,
+            extra_request_fields: None,
+            short_response_retries: None,
+            retry_model: None,
+            max_concurrency: None,
+            diff_truncation: None,
+            hard_max_diff_bytes: None,
+            model_cache_ttl_hours: None,
+            http_version: None,
+            request_timeout_secs: None,
+            edit_scissors: None,
+            max_diff_tokens: None,
         }
     }
 }
 
 impl Config {
-    fn config_path() -> Result<PathBuf> {
+    pub fn config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?
             .join("convmit");
@@ -60,10 +148,51 @@ impl Config {
         Ok(())
     }
 
+    pub fn get_allow_env_keys(&self) -> bool {
+        self.allow_env_keys.unwrap_or(true)
+    }
+
+    pub fn get_load_dotenv(&self) -> bool {
+        self.load_dotenv.unwrap_or(false)
+    }
+
+    // Variables already set in the environment win over the .env file
+    pub fn load_dotenv(&self, repo_root: &Path) -> Result<bool> {
+        let path = repo_root.join(".env");
+        if !self.get_load_dotenv() || !path.exists() {
+            return Ok(false);
+        }
+
+        dotenvy::from_path(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", path.display(), e))?;
+        Ok(true)
+    }
+
+    fn resolve_key(&self, value: &Option<String>, env_var: &str) -> Option<(String, Source)> {
+        value.clone().map(|key| (key, Source::Config)).or_else(|| {
+            if !self.get_allow_env_keys() {
+                return None;
+            }
+            std::env::var(env_var).ok().map(|key| (key, Source::Env))
+        })
+    }
+
     pub fn get_claude_api_key(&self) -> Option<String> {
-        self.claude_api_key
-            .clone()
-            .or(std::env::var("CLAUDE_API_KEY").ok())
+        self.resolve_key(&self.claude_api_key, "CLAUDE_API_KEY")
+            .map(|(key, _)| key)
+    }
+
+    pub fn clear_keys(&mut self) -> Result<()> {
+        self.reset_keys();
+        self.save()
+    }
+
+    fn reset_keys(&mut self) {
+        self.claude_api_key = None;
+        self.openai_api_key = None;
+        self.gemini_api_key = None;
+        self.mistral_api_key = None;
+        self.cohere_api_key = None;
     }
 
     pub fn set_claude_api_key(&mut self, key: String) -> Result<()> {
@@ -72,9 +201,8 @@ impl Config {
     }
 
     pub fn get_openai_api_key(&self) -> Option<String> {
-        self.openai_api_key
-            .clone()
-            .or(std::env::var("OPENAI_API_KEY").ok())
+        self.resolve_key(&self.openai_api_key, "OPENAI_API_KEY")
+            .map(|(key, _)| key)
     }
 
     pub fn set_openai_api_key(&mut self, key: String) -> Result<()> {
@@ -83,9 +211,8 @@ impl Config {
     }
 
     pub fn get_gemini_api_key(&self) -> Option<String> {
-        self.gemini_api_key
-            .clone()
-            .or(std::env::var("GEMINI_API_KEY").ok())
+        self.resolve_key(&self.gemini_api_key, "GEMINI_API_KEY")
+            .map(|(key, _)| key)
     }
 
     pub fn set_gemini_api_key(&mut self, key: String) -> Result<()> {
@@ -93,7 +220,16 @@ impl Config {
         self.save()
     }
 
+    // Self-hosted OpenAI-compatible servers such as vLLM usually run without a key
+    pub fn requires_api_key(&self, model: &crate::ai::Model) -> bool {
+        model.requires_api_key() && !(model.is_openai() && self.get_openai_base_url().is_some())
+    }
+
     pub fn validate_model_config(&self, model: &crate::ai::Model) -> Result<()> {
+        if !self.requires_api_key(model) {
+            return Ok(());
+        }
+
         match model {
             m if m.is_claude() && self.get_claude_api_key().is_none() => Err(anyhow::anyhow!(
                 "Claude API key required for {}. Set with --set-claude-key or CLAUDE_API_KEY env var",
@@ -111,14 +247,17 @@ impl Config {
                 "Mistral API key required for {}. Set with --set-mistral-key or MISTRAL_API_KEY env var",
                 model
             )),
+            m if m.is_cohere() && self.get_cohere_api_key().is_none() => Err(anyhow::anyhow!(
+                "Cohere API key required for {}. Set with --set-cohere-key or COHERE_API_KEY env var",
+                model
+            )),
             _ => Ok(()),
         }
     }
 
     pub fn get_mistral_api_key(&self) -> Option<String> {
-        self.mistral_api_key
-            .clone()
-            .or(std::env::var("MISTRAL_API_KEY").ok())
+        self.resolve_key(&self.mistral_api_key, "MISTRAL_API_KEY")
+            .map(|(key, _)| key)
     }
 
     pub fn set_mistral_api_key(&mut self, key: String) -> Result<()> {
@@ -126,6 +265,16 @@ impl Config {
         self.save()
     }
 
+    pub fn get_cohere_api_key(&self) -> Option<String> {
+        self.resolve_key(&self.cohere_api_key, "COHERE_API_KEY")
+            .map(|(key, _)| key)
+    }
+
+    pub fn set_cohere_api_key(&mut self, key: String) -> Result<()> {
+        self.cohere_api_key = Some(key);
+        self.save()
+    }
+
     pub fn get_api_key_for_model(&self, model: &crate::ai::Model) -> Option<String> {
         if model.is_claude() {
             self.get_claude_api_key()
@@ -135,17 +284,177 @@ impl Config {
             self.get_gemini_api_key()
         } else if model.is_mistral() {
             self.get_mistral_api_key()
+        } else if model.is_cohere() {
+            self.get_cohere_api_key()
+        } else {
+            None
+        }
+    }
+
+    // `None` marks a setting with no value at all, e.g. a provider key that isn't set anywhere
+    pub fn sources(&self, cli_model: bool) -> Vec<(&'static str, Option<Source>)> {
+        let keys = [
+            ("claude_api_key", &self.claude_api_key, "CLAUDE_API_KEY"),
+            ("openai_api_key", &self.openai_api_key, "OPENAI_API_KEY"),
+            ("gemini_api_key", &self.gemini_api_key, "GEMINI_API_KEY"),
+            ("mistral_api_key", &self.mistral_api_key, "MISTRAL_API_KEY"),
+            ("cohere_api_key", &self.cohere_api_key, "COHERE_API_KEY"),
+        ];
+        let mut sources: Vec<_> = keys
+            .into_iter()
+            .map(|(name, value, env_var)| {
+                (
+                    name,
+                    self.resolve_key(value, env_var).map(|(_, source)| source),
+                )
+            })
+            .collect();
+
+        let default_model = if cli_model {
+            Source::Cli
+        } else if self.default_model.is_some()
+            || self
+                .host_default_model(current_hostname().as_deref())
+                .is_some()
+        {
+            Source::Config
+        } else {
+            Source::Default
+        };
+        sources.push(("default_model", Some(default_model)));
+
+        sources.push((
+            "openai_base_url",
+            self.openai_base_url_source("OPENAI_BASE_URL"),
+        ));
+        sources
+    }
+
+    fn openai_base_url_source(&self, env_var: &str) -> Option<Source> {
+        if self.openai_base_url.is_some() {
+            Some(Source::Config)
+        } else {
+            std::env::var(env_var).ok().map(|_| Source::Env)
+        }
+    }
+
+    pub fn key_source(&self, model: &crate::ai::Model) -> Option<Source> {
+        let (value, env_var) = if model.is_claude() {
+            (&self.claude_api_key, "CLAUDE_API_KEY")
+        } else if model.is_openai() {
+            (&self.openai_api_key, "OPENAI_API_KEY")
+        } else if model.is_gemini() {
+            (&self.gemini_api_key, "GEMINI_API_KEY")
+        } else if model.is_mistral() {
+            (&self.mistral_api_key, "MISTRAL_API_KEY")
+        } else if model.is_cohere() {
+            (&self.cohere_api_key, "COHERE_API_KEY")
+        } else {
+            return None;
+        };
+
+        self.resolve_key(value, env_var).map(|(_, source)| source)
+    }
+
+    pub fn get_base_url_for_model(&self, model: &crate::ai::Model) -> Option<String> {
+        if model.is_local() {
+            self.llamacpp_base_url.clone()
+        } else if model.is_openai() {
+            self.get_openai_base_url()
+        } else if model.is_gemini() {
+            self.gemini_base_url.clone()
         } else {
             None
         }
     }
 
+    // Same variable the official OpenAI SDKs read, so vLLM and similar setups work unchanged
+    pub fn get_openai_base_url(&self) -> Option<String> {
+        self.openai_base_url
+            .clone()
+            .or_else(|| std::env::var("OPENAI_BASE_URL").ok())
+            .filter(|url| !url.trim().is_empty())
+    }
+
+    pub fn get_normalize_scope(&self) -> bool {
+        self.normalize_scope.unwrap_or(false)
+    }
+
+    pub fn get_edit_scissors(&self) -> bool {
+        self.edit_scissors.unwrap_or(true)
+    }
+
+    pub fn get_attribution(&self) -> bool {
+        self.attribution.unwrap_or(false)
+    }
+
+    pub fn get_description_style(&self) -> DescriptionStyle {
+        self.description_style.unwrap_or_default()
+    }
+
+    pub fn get_commit_method(&self) -> CommitMethod {
+        self.commit_method.unwrap_or_default()
+    }
+
+    pub fn get_http_version(&self) -> HttpVersion {
+        self.http_version.unwrap_or_default()
+    }
+
+    pub fn get_short_response_retries(&self) -> u32 {
+        self.short_response_retries.unwrap_or(1)
+    }
+
+    pub fn get_extra_request_fields(&self) -> ExtraFields {
+        self.extra_request_fields.clone().unwrap_or_default()
+    }
+
     pub fn get_default_model(&self) -> crate::ai::Model {
         self.default_model
             .clone()
             .unwrap_or(crate::ai::Model::Haiku4_5)
     }
 
+    pub fn resolve_default_model(&self) -> crate::ai::Model {
+        self.default_model_for_host(current_hostname().as_deref())
+    }
+
+    // Hostnames are case-insensitive, and some systems report the FQDN where users write the short name
+    pub fn default_model_for_host(&self, host: Option<&str>) -> crate::ai::Model {
+        self.host_default_model(host)
+            .unwrap_or_else(|| self.get_default_model())
+    }
+
+    fn host_default_model(&self, host: Option<&str>) -> Option<crate::ai::Model> {
+        let lookup = |name: &str| {
+            self.default_model_by_host
+                .as_ref()?
+                .iter()
+                .find(|(host, _)| host.eq_ignore_ascii_case(name))
+                .map(|(_, model)| model.clone())
+        };
+
+        host.and_then(|host| lookup(host).or_else(|| lookup(host.split('.').next()?)))
+    }
+
+    pub fn get_max_concurrency(&self) -> usize {
+        self.max_concurrency.unwrap_or(3).max(1)
+    }
+
+    pub fn get_diff_truncation(&self) -> TruncationStrategy {
+        self.diff_truncation.unwrap_or_default()
+    }
+
+    pub fn get_model_cache_ttl(&self) -> std::time::Duration {
+        self.model_cache_ttl_hours
+            .map_or(crate::model_cache::DEFAULT_TTL, |hours| {
+                std::time::Duration::from_secs(hours * 60 * 60)
+            })
+    }
+
+    pub fn get_retry_model(&self) -> Option<crate::ai::Model> {
+        self.retry_model.clone()
+    }
+
     pub fn set_default_model(&mut self, model: crate::ai::Model) -> Result<()> {
         self.default_model = Some(model);
         self.save()
@@ -157,6 +466,38 @@ mod tests {
     use super::*;
     use crate::ai::Model;
 
+    #[test]
+    fn test_dotenv_supplies_key_only_when_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(".env"),
+            "COHERE_API_KEY=dotenv-cohere-key\n",
+        )
+        .unwrap();
+
+        unsafe {
+            std::env::remove_var("COHERE_API_KEY");
+        }
+
+        let disabled = Config::default();
+        assert!(!disabled.load_dotenv(dir.path()).unwrap());
+        assert_eq!(disabled.get_cohere_api_key(), None);
+
+        let enabled = Config {
+            load_dotenv: Some(true),
+            ..Default::default()
+        };
+        assert!(enabled.load_dotenv(dir.path()).unwrap());
+        assert_eq!(
+            enabled.get_cohere_api_key(),
+            Some("dotenv-cohere-key".to_string())
+        );
+
+        unsafe {
+            std::env::remove_var("COHERE_API_KEY");
+        }
+    }
+
     fn create_test_config() -> Config {
         Config {
             claude_api_key: Some("test-claude-key".to_string()),
@@ -164,6 +505,7 @@ mod tests {
             gemini_api_key: Some("test-gemini-key".to_string()),
             mistral_api_key: Some("test-mistral-key".to_string()),
             default_model: Some(Model::Sonnet4),
+            ..Default::default()
         }
     }
 
@@ -174,6 +516,7 @@ mod tests {
             gemini_api_key: None,
             mistral_api_key: None,
             default_model: None,
+            ..Default::default()
         }
     }
 
@@ -348,6 +691,7 @@ mod tests {
             gemini_api_key: None,
             mistral_api_key: None,
             default_model: None,
+            ..Default::default()
         };
 
         let openai_only_config = Config {
@@ -356,6 +700,7 @@ mod tests {
             gemini_api_key: None,
             mistral_api_key: None,
             default_model: None,
+            ..Default::default()
         };
 
         let gemini_only_config = Config {
@@ -364,6 +709,7 @@ mod tests {
             gemini_api_key: Some("gemini-key".to_string()),
             mistral_api_key: None,
             default_model: None,
+            ..Default::default()
         };
 
         let mistral_only_config = Config {
@@ -372,6 +718,7 @@ mod tests {
             gemini_api_key: None,
             mistral_api_key: Some("mistral-key".to_string()),
             default_model: None,
+            ..Default::default()
         };
 
         // Claude-only config
@@ -433,6 +780,190 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_local_models_skip_key_validation() {
+        let config = create_empty_config();
+        let model = Model::Local("llama-3".to_string());
+
+        assert!(config.validate_model_config(&model).is_ok());
+        assert_eq!(config.get_api_key_for_model(&model), None);
+        assert_eq!(config.get_base_url_for_model(&model), None);
+
+        let config = Config {
+            llamacpp_base_url: Some("http://localhost:9000/v1".to_string()),
+            ..create_empty_config()
+        };
+        assert_eq!(
+            config.get_base_url_for_model(&model),
+            Some("http://localhost:9000/v1".to_string())
+        );
+        assert_eq!(config.get_base_url_for_model(&Model::Gpt5), None);
+
+        let config = Config {
+            gemini_base_url: Some("https://gateway.example.com/gemini/".to_string()),
+            ..create_empty_config()
+        };
+        assert_eq!(
+            config.get_base_url_for_model(&Model::Gemini2_5Pro),
+            Some("https://gateway.example.com/gemini/".to_string())
+        );
+        assert_eq!(config.get_base_url_for_model(&model), None);
+
+        let config = Config {
+            openai_base_url: Some("http://my-server:8000/v1".to_string()),
+            ..create_empty_config()
+        };
+        assert_eq!(
+            config.get_base_url_for_model(&Model::Gpt5),
+            Some("http://my-server:8000/v1".to_string())
+        );
+        assert_eq!(config.get_base_url_for_model(&model), None);
+    }
+
+    #[test]
+    fn test_custom_openai_base_url_skips_key_validation() {
+        let config = Config {
+            allow_env_keys: Some(false),
+            openai_base_url: Some("http://my-server:8000/v1".to_string()),
+            ..create_empty_config()
+        };
+
+        assert!(!config.requires_api_key(&Model::Gpt5));
+        assert!(config.validate_model_config(&Model::Gpt5).is_ok());
+        assert_eq!(config.get_api_key_for_model(&Model::Gpt5), None);
+        assert!(config.validate_model_config(&Model::Sonnet4).is_err());
+    }
+
+    #[test]
+    fn test_ollama_models_skip_key_validation() {
+        let config = create_empty_config();
+
+        for model in [Model::OllamaLlama3, Model::OllamaQwen2_5Coder] {
+            assert!(config.validate_model_config(&model).is_ok());
+            assert_eq!(config.get_api_key_for_model(&model), None);
+            assert!(!model.requires_api_key());
+        }
+    }
+
+    #[test]
+    fn test_key_source_config() {
+        let config = create_test_config();
+
+        assert_eq!(config.key_source(&Model::Sonnet4), Some(Source::Config));
+        assert_eq!(config.key_source(&Model::Gpt5), Some(Source::Config));
+        assert_eq!(config.key_source(&Model::Local("llama".to_string())), None);
+    }
+
+    #[test]
+    fn test_key_source_env() {
+        let config = create_empty_config();
+
+        unsafe {
+            std::env::set_var("GEMINI_API_KEY", "env-gemini-key");
+        }
+
+        assert_eq!(config.key_source(&Model::Gemini2_5Flash), Some(Source::Env));
+
+        unsafe {
+            std::env::remove_var("GEMINI_API_KEY");
+        }
+
+        assert_eq!(config.key_source(&Model::Gemini2_5Flash), None);
+    }
+
+    #[test]
+    fn test_sources_report_env_and_config() {
+        // A test-only variable, other tests read OPENAI_BASE_URL itself
+        let env_var = "CONVMIT_TEST_OPENAI_BASE_URL";
+        let mut config = create_empty_config();
+        config.claude_api_key = Some("config-claude-key".to_string());
+        unsafe {
+            std::env::set_var(env_var, "http://localhost:8000/v1");
+        }
+
+        let sources = config.sources(false);
+        let source = |name: &str| {
+            sources
+                .iter()
+                .find(|(setting, _)| *setting == name)
+                .unwrap()
+                .1
+        };
+
+        assert_eq!(source("claude_api_key"), Some(Source::Config));
+        assert_eq!(config.openai_base_url_source(env_var), Some(Source::Env));
+        assert_eq!(source("default_model"), Some(Source::Default));
+        assert_eq!(
+            config.sources(true)[5],
+            ("default_model", Some(Source::Cli))
+        );
+
+        config.openai_base_url = Some("http://my-server:8000/v1".to_string());
+        assert_eq!(config.openai_base_url_source(env_var), Some(Source::Config));
+
+        unsafe {
+            std::env::remove_var(env_var);
+        }
+    }
+
+    #[test]
+    fn test_env_keys_ignored_when_disallowed() {
+        let env_var = "CONVMIT_TEST_DISALLOWED_ENV_KEY";
+        unsafe {
+            std::env::set_var(env_var, "env-key");
+        }
+
+        let allowed = create_empty_config();
+        assert_eq!(
+            allowed.resolve_key(&None, env_var),
+            Some(("env-key".to_string(), Source::Env))
+        );
+
+        let locked_down = Config {
+            allow_env_keys: Some(false),
+            ..create_empty_config()
+        };
+        assert_eq!(locked_down.resolve_key(&None, env_var), None);
+        assert_eq!(
+            locked_down.resolve_key(&Some("config-key".to_string()), env_var),
+            Some(("config-key".to_string(), Source::Config))
+        );
+
+        unsafe {
+            std::env::remove_var(env_var);
+        }
+    }
+
+    #[test]
+    fn test_reset_keys_removes_stored_keys() {
+        let mut config = Config {
+            allow_env_keys: Some(false),
+            ..create_test_config()
+        };
+        config.gemini_api_key = Some("gemini-key".to_string());
+        config.mistral_api_key = Some("mistral-key".to_string());
+
+        config.reset_keys();
+
+        assert_eq!(config.get_claude_api_key(), None);
+        assert_eq!(config.get_openai_api_key(), None);
+        assert_eq!(config.get_gemini_api_key(), None);
+        assert_eq!(config.get_mistral_api_key(), None);
+        assert_eq!(config.get_default_model(), Model::Sonnet4);
+    }
+
+    #[test]
+    fn test_validate_fails_with_env_keys_disallowed() {
+        let config = Config {
+            allow_env_keys: Some(false),
+            ..create_empty_config()
+        };
+
+        assert!(config.validate_model_config(&Model::Sonnet4).is_err());
+        assert_eq!(config.get_api_key_for_model(&Model::Gpt5), None);
+        assert_eq!(config.key_source(&Model::MistralMedium3_1), None);
+    }
+
     #[test]
     fn test_get_default_model() {
         let config = create_test_config();
@@ -445,12 +976,93 @@ mod tests {
         assert_eq!(config.get_default_model(), Model::Haiku4_5);
     }
 
+    // Same serialization as save() and load(), without touching the user's config file
+    #[test]
+    fn test_default_model_persists_through_toml() {
+        for model in [Model::Sonnet4, Model::Local("llama-3".to_string())] {
+            let config = Config {
+                default_model: Some(model.clone()),
+                ..Config::default()
+            };
+
+            let reloaded: Config =
+                toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+            assert_eq!(reloaded.get_default_model(), model);
+        }
+    }
+
+    #[test]
+    fn test_default_model_by_host() {
+        let config = Config {
+            default_model_by_host: Some(BTreeMap::from([
+                ("work-laptop".to_string(), Model::Gpt5Mini),
+                ("build.example.com".to_string(), Model::Opus4_1),
+            ])),
+            ..create_test_config()
+        };
+
+        assert_eq!(
+            config.default_model_for_host(Some("work-laptop")),
+            Model::Gpt5Mini
+        );
+        assert_eq!(
+            config.default_model_for_host(Some("Work-Laptop.corp.example.com")),
+            Model::Gpt5Mini
+        );
+        assert_eq!(
+            config.default_model_for_host(Some("build.example.com")),
+            Model::Opus4_1
+        );
+        assert_eq!(
+            config.default_model_for_host(Some("home-server")),
+            Model::Sonnet4
+        );
+        assert_eq!(config.default_model_for_host(None), Model::Sonnet4);
+        assert_eq!(
+            create_test_config().default_model_for_host(Some("work-laptop")),
+            Model::Sonnet4
+        );
+    }
+
     #[test]
     fn test_get_default_model_with_default() {
         let config = Config::default();
         assert_eq!(config.get_default_model(), Model::Haiku4_5);
     }
 
+    #[test]
+    fn test_get_gemini_api_key_from_config() {
+        let config = create_test_config();
+        assert_eq!(
+            config.get_gemini_api_key(),
+            Some("test-gemini-key".to_string())
+        );
+        assert_eq!(
+            config.get_api_key_for_model(&Model::Gemini2_5Flash),
+            Some("test-gemini-key".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_gemini_model_config() {
+        let config = create_test_config();
+        assert!(config.validate_model_config(&Model::Gemini2_5Pro).is_ok());
+
+        // Other tests set the provider key variables, so ignore the environment here
+        let empty_config = Config {
+            allow_env_keys: Some(false),
+            ..create_empty_config()
+        };
+        let result = empty_config.validate_model_config(&Model::Gemini2_5Pro);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Gemini API key required")
+        );
+    }
+
     #[test]
     fn test_get_mistral_api_key_from_config() {
         let config = create_test_config();
@@ -489,7 +1101,11 @@ mod tests {
                 .is_ok()
         );
 
-        let empty_config = create_empty_config();
+        // Other tests set the provider key variables, so ignore the environment here
+        let empty_config = Config {
+            allow_env_keys: Some(false),
+            ..create_empty_config()
+        };
         let result = empty_config.validate_model_config(&Model::MistralMedium3_1);
         assert!(result.is_err());
         assert!(
@@ -499,4 +1115,36 @@ mod tests {
                 .contains("Mistral API key required")
         );
     }
+
+    #[test]
+    fn test_extra_request_fields_round_trip() {
+        let config: Config = toml::from_str(
+            r#"
+default_model = "Sonnet4"
+
+[extra_request_fields]
+top_p = 0.9
+frequency_penalty = 0.1
+"#,
+        )
+        .unwrap();
+
+        let fields = config.get_extra_request_fields();
+        assert_eq!(fields["top_p"], 0.9);
+        assert_eq!(fields["frequency_penalty"], 0.1);
+
+        let saved: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(saved.get_extra_request_fields(), fields);
+        assert!(Config::default().get_extra_request_fields().is_empty());
+    }
+
+    #[test]
+    fn test_http_version_parses_from_config() {
+        let config: Config = toml::from_str(r#"http_version = "http1""#).unwrap();
+        assert_eq!(config.get_http_version(), HttpVersion::Http1);
+
+        let config: Config = toml::from_str(r#"http_version = "auto""#).unwrap();
+        assert_eq!(config.get_http_version(), HttpVersion::Auto);
+        assert_eq!(Config::default().get_http_version(), HttpVersion::Auto);
+    }
 }
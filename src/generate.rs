@@ -0,0 +1,1111 @@
+use std::future::Future;
+
+use futures_util::future::join_all;
+use tokio::sync::Semaphore;
+
+use crate::ai::{GenerateCommitMessage, Model, Prompt, TokenSink, Usage};
+use crate::commit::ConventionalCommit;
+use crate::output;
+
+// Conventional exit status for a process interrupted by SIGINT
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+// Rough average for source code, providers don't offer a local tokenizer to count exactly
+const BYTES_PER_TOKEN: usize = 4;
+
+// Room for the file list, tags and extra context around the diff in the user prompt
+const PROMPT_OVERHEAD_TOKENS: usize = 2_000;
+
+// Reserved for the reply when the output is uncapped
+const UNCAPPED_OUTPUT_TOKENS: usize = 8_192;
+
+// Shorter than the smallest useful "type: subject" message
+const MIN_MESSAGE_LENGTH: usize = 10;
+
+const CLASSIFY_REQUEST: &str = "Before writing the message, classify this change. Reply with only the commit type, optional scope and '!' for breaking changes followed by a colon, for example \"fix(parser):\", and nothing else.";
+
+// The format lines from the commit and merge prompts and their common spellings,
+// echoed back unfilled
+const PLACEHOLDER_SUBJECTS: &[&str] = &[
+    "type(scope): description",
+    "type(scope)!: description",
+    "type: description",
+    "<type>(<scope>): <description>",
+    "<type>: <description>",
+    "merge: description",
+    "merge: <description>",
+];
+
+const SHORT_RESPONSE_NUDGE: &str = "Your previous reply was empty, too short or only repeated the format. Reply with a complete conventional commit message, for example \"fix(parser): handle empty input\".";
+
+const FORMAT_NUDGE: &str = "Your previous reply was not a conventional commit message. Reply with only the commit message, starting with a subject line such as \"fix(parser): handle empty input\".";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationResult {
+    pub message: String,
+    pub model: Model,
+    pub usage: Option<Usage>,
+    pub parsed: Option<ConventionalCommit>,
+}
+
+impl GenerationResult {
+    pub fn new(message: String, model: Model, usage: Option<Usage>) -> Self {
+        let parsed = ConventionalCommit::parse(&message).ok();
+        Self {
+            message,
+            model,
+            usage,
+            parsed,
+        }
+    }
+
+    // Escalation and --model chains can hand the message to another model, and
+    // --model-name only ever renames the main one
+    pub fn api_id(&self, primary: &Model, model_name: Option<&str>) -> String {
+        match model_name {
+            Some(name) if self.model == *primary => name.to_string(),
+            _ => self.model.to_api_str(),
+        }
+    }
+}
+
+// Lets the retry and escalation logic drive streamed requests like any other
+pub struct Streaming<'a> {
+    client: &'a dyn GenerateCommitMessage,
+    on_token: &'a TokenSink<'a>,
+}
+
+impl<'a> Streaming<'a> {
+    pub fn new(client: &'a dyn GenerateCommitMessage, on_token: &'a TokenSink<'a>) -> Self {
+        Self { client, on_token }
+    }
+}
+
+#[async_trait::async_trait]
+impl GenerateCommitMessage for Streaming<'_> {
+    async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+        Ok(self.generate_with_usage(prompt).await?.0)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &Prompt,
+    ) -> anyhow::Result<(String, Option<Usage>)> {
+        self.client.generate_streaming(prompt, self.on_token).await
+    }
+}
+
+// Stands in for a --model fallback that could not be set up, so its error only
+// surfaces once the chain actually reaches it
+pub struct Unavailable {
+    reason: String,
+}
+
+impl Unavailable {
+    pub fn new(err: anyhow::Error) -> Self {
+        Self {
+            reason: err.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl GenerateCommitMessage for Unavailable {
+    async fn generate_commit_message(&self, _prompt: &Prompt) -> anyhow::Result<String> {
+        Err(anyhow::anyhow!("{}", self.reason))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Cancellable<T> {
+    Completed(T),
+    Cancelled,
+}
+
+pub async fn run_cancellable<T>(
+    work: impl Future<Output = T>,
+    cancel: impl Future<Output = ()>,
+) -> Cancellable<T> {
+    tokio::select! {
+        biased;
+        _ = cancel => Cancellable::Cancelled,
+        output = work => Cancellable::Completed(output),
+    }
+}
+
+// The commit step only ever sees a generation that finished, a cancelled one skips it
+pub fn commit_if_completed<T, R>(
+    outcome: Cancellable<T>,
+    commit: impl FnOnce(T) -> R,
+) -> Cancellable<R> {
+    match outcome {
+        Cancellable::Completed(output) => Cancellable::Completed(commit(output)),
+        Cancellable::Cancelled => Cancellable::Cancelled,
+    }
+}
+
+pub async fn ctrl_c() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        // Without a signal handler there is nothing to wait for, so never cancel
+        std::future::pending::<()>().await;
+    }
+}
+
+pub fn diff_byte_budget(
+    context_window: Option<u32>,
+    system_prompt: &str,
+    max_output_tokens: Option<u32>,
+) -> Option<usize> {
+    let context_window = usize::try_from(context_window?).ok()?;
+    let reserved = max_output_tokens.map_or(UNCAPPED_OUTPUT_TOKENS, |tokens| tokens as usize)
+        + system_prompt.len().div_ceil(BYTES_PER_TOKEN)
+        + PROMPT_OVERHEAD_TOKENS;
+
+    Some(context_window.saturating_sub(reserved) * BYTES_PER_TOKEN)
+}
+
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(BYTES_PER_TOKEN)
+}
+
+// The tighter of the context window budget and an explicit --max-diff-tokens cap
+pub fn cap_diff_budget(
+    context_budget: Option<usize>,
+    max_diff_tokens: Option<usize>,
+) -> Option<usize> {
+    let token_budget = max_diff_tokens.map(|tokens| tokens.saturating_mul(BYTES_PER_TOKEN));
+    match (context_budget, token_budget) {
+        (Some(context), Some(tokens)) => Some(context.min(tokens)),
+        (context, tokens) => context.or(tokens),
+    }
+}
+
+pub fn is_too_short(message: &str) -> bool {
+    message.trim().chars().count() < MIN_MESSAGE_LENGTH
+}
+
+pub fn is_placeholder(message: &str) -> bool {
+    let subject = message.trim().lines().next().unwrap_or_default();
+    let subject = subject.trim().trim_matches('`').to_lowercase();
+    PLACEHOLDER_SUBJECTS.contains(&subject.as_str())
+}
+
+fn needs_retry(message: &str) -> bool {
+    is_too_short(message) || is_placeholder(message)
+}
+
+pub fn is_usable(message: &str) -> bool {
+    !needs_retry(message) && ConventionalCommit::parse(message).is_ok()
+}
+
+pub async fn generate(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    prompt: &Prompt,
+) -> anyhow::Result<GenerationResult> {
+    let (message, usage) = client.generate_with_usage(prompt).await?;
+    Ok(GenerationResult::new(message, model.clone(), usage))
+}
+
+pub async fn generate_with_retry(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    prompt: &Prompt,
+    retries: u32,
+) -> anyhow::Result<GenerationResult> {
+    retry_while(
+        client,
+        model,
+        prompt,
+        retries,
+        needs_retry,
+        SHORT_RESPONSE_NUDGE,
+    )
+    .await
+}
+
+// Stricter than generate_with_retry, anything that doesn't parse as a conventional commit
+// is sent back with a correction
+async fn generate_until_usable(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    prompt: &Prompt,
+    retries: u32,
+) -> anyhow::Result<GenerationResult> {
+    retry_while(
+        client,
+        model,
+        prompt,
+        retries,
+        |message| !is_usable(message),
+        FORMAT_NUDGE,
+    )
+    .await
+}
+
+async fn retry_while(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    prompt: &Prompt,
+    retries: u32,
+    rejected: impl Fn(&str) -> bool,
+    nudge: &str,
+) -> anyhow::Result<GenerationResult> {
+    let mut result = generate(client, model, prompt).await?;
+
+    let nudged = Prompt {
+        system: prompt.system.clone(),
+        user: format!("{}\n\n{}", prompt.user, nudge),
+    };
+    for _ in 0..retries {
+        if !rejected(&result.message) {
+            break;
+        }
+        // Every attempt is billed, not just the one that is kept
+        let usage = result.usage;
+        result = generate(client, model, &nudged).await?;
+        result.usage = add_usage(usage, result.usage);
+    }
+
+    Ok(result)
+}
+
+// Without a retry model the reply is only retried when short or a placeholder. With one,
+// the primary first gets its own corrective retries, and the run fails if the retry
+// model can't produce a conventional commit either
+pub async fn generate_with_escalation(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    retry: Option<(&dyn GenerateCommitMessage, &Model)>,
+    prompt: &Prompt,
+    retries: u32,
+) -> anyhow::Result<GenerationResult> {
+    let Some((retry_client, retry_model)) = retry else {
+        return generate_with_retry(client, model, prompt, retries).await;
+    };
+
+    let result = generate_until_usable(client, model, prompt, retries).await?;
+    if is_usable(&result.message) {
+        return Ok(result);
+    }
+
+    output::warning(&format!(
+        "{model} did not return a conventional commit, trying {retry_model}"
+    ));
+    let usage = result.usage;
+    let mut result = generate_until_usable(retry_client, retry_model, prompt, retries).await?;
+    result.usage = add_usage(usage, result.usage);
+    if is_usable(&result.message) {
+        return Ok(result);
+    }
+
+    Err(anyhow::anyhow!(
+        "Neither {model} nor {retry_model} returned a conventional commit message, the last reply was {:?}",
+        result.message.trim()
+    ))
+}
+
+// Later models in a --model chain only get a turn when every model before them failed outright
+pub async fn generate_with_fallbacks(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    fallbacks: &[(&dyn GenerateCommitMessage, &Model)],
+    retry: Option<(&dyn GenerateCommitMessage, &Model)>,
+    prompt: &Prompt,
+    retries: u32,
+) -> anyhow::Result<GenerationResult> {
+    let mut result = generate_with_escalation(client, model, retry, prompt, retries).await;
+    let mut failed = model;
+
+    for &(fallback_client, fallback_model) in fallbacks {
+        let Err(err) = &result else {
+            break;
+        };
+        output::warning(&format!("{failed} failed ({err}), trying {fallback_model}"));
+        result =
+            generate_with_escalation(fallback_client, fallback_model, retry, prompt, retries).await;
+        failed = fallback_model;
+    }
+
+    result
+}
+
+// "fix(parser):" or "feat!: ..." from the first phase, anything after the colon is ignored
+pub fn parse_classification(reply: &str) -> anyhow::Result<ConventionalCommit> {
+    let line = reply.trim().lines().next().unwrap_or_default();
+    let prefix = line.split(':').next().unwrap_or_default().trim();
+    ConventionalCommit::parse(&format!("{prefix}: classification"))
+        .map_err(|err| anyhow::anyhow!("Could not read a classification from {line:?}: {err}"))
+}
+
+fn classification_prefix(classification: &ConventionalCommit) -> String {
+    // subject() renders "type(scope)!: description", keep everything up to the colon
+    let subject = classification.subject();
+    subject[..subject.len() - classification.description.len()]
+        .trim_end()
+        .to_string()
+}
+
+pub fn assemble_two_phase(classification: &ConventionalCommit, reply: &str) -> String {
+    let mut commit = ConventionalCommit::parse(reply).unwrap_or_else(|_| {
+        let mut lines = reply.trim().splitn(2, '\n');
+        ConventionalCommit {
+            description: lines.next().unwrap_or_default().trim().to_string(),
+            body: lines
+                .next()
+                .map(|body| body.trim().to_string())
+                .filter(|body| !body.is_empty()),
+            ..classification.clone()
+        }
+    });
+
+    commit.commit_type = classification.commit_type.clone();
+    commit.scope = classification.scope.clone();
+    commit.breaking |= classification.breaking;
+    commit.to_string()
+}
+
+fn add_usage(first: Option<Usage>, second: Option<Usage>) -> Option<Usage> {
+    let (first, second) = (first?, second?);
+    Some(Usage {
+        input_tokens: first.input_tokens + second.input_tokens,
+        output_tokens: first.output_tokens + second.output_tokens,
+    })
+}
+
+// A cheap first call settles type and scope, the second writes the message within them.
+// The second call goes through the same nudges and escalation as a single request, so a
+// reply without a type gets a correction before its first line turns into the description
+pub async fn generate_two_phase(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    retry: Option<(&dyn GenerateCommitMessage, &Model)>,
+    prompt: &Prompt,
+    retries: u32,
+) -> anyhow::Result<GenerationResult> {
+    let classify = Prompt {
+        system: prompt.system.clone(),
+        user: format!("{}\n\n{}", prompt.user, CLASSIFY_REQUEST),
+    };
+    let (reply, classify_usage) = client.generate_with_usage(&classify).await?;
+    let classification = parse_classification(&reply)?;
+
+    let describe = Prompt {
+        system: prompt.system.clone(),
+        user: format!(
+            "{}\n\nThis change is classified as \"{}\". Write the commit message using exactly that type and scope.",
+            prompt.user,
+            classification_prefix(&classification)
+        ),
+    };
+    let described = match retry {
+        Some(_) => generate_with_escalation(client, model, retry, &describe, retries).await?,
+        None => generate_until_usable(client, model, &describe, retries).await?,
+    };
+
+    Ok(GenerationResult::new(
+        assemble_two_phase(&classification, &described.message),
+        described.model,
+        add_usage(classify_usage, described.usage),
+    ))
+}
+
+// Fans out one request per prompt, each with the usual retries and escalation, without
+// sending more than `max_concurrency` at a time
+pub async fn generate_concurrently(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    retry: Option<(&dyn GenerateCommitMessage, &Model)>,
+    prompts: &[Prompt],
+    max_concurrency: usize,
+    retries: u32,
+) -> Vec<anyhow::Result<GenerationResult>> {
+    let semaphore = Semaphore::new(max_concurrency.max(1));
+
+    join_all(prompts.iter().map(|prompt| async {
+        let _permit = semaphore.acquire().await?;
+        generate_with_escalation(client, model, retry, prompt, retries).await
+    }))
+    .await
+}
+
+// Each candidate gets the usual short-reply retries. Failed requests are dropped as long
+// as one candidate came back, unusable replies as long as a usable one did, and repeats
+// only count once. The usage covers every reply, including the dropped ones
+pub async fn generate_candidates(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    prompt: &Prompt,
+    count: usize,
+    max_concurrency: usize,
+    retries: u32,
+) -> anyhow::Result<(Vec<GenerationResult>, Option<Usage>)> {
+    let semaphore = Semaphore::new(max_concurrency.max(1));
+    let results = join_all((0..count).map(|_| async {
+        let _permit = semaphore.acquire().await?;
+        generate_with_retry(client, model, prompt, retries).await
+    }))
+    .await;
+
+    let mut candidates: Vec<GenerationResult> = Vec::new();
+    let mut usage = Some(Usage::default());
+    let mut last_error = None;
+    for result in results {
+        match result {
+            Ok(mut result) => {
+                usage = add_usage(usage, result.usage);
+                result.message = result.message.trim().to_string();
+                let repeated = candidates
+                    .iter()
+                    .any(|candidate| candidate.message == result.message);
+                if !result.message.is_empty() && !repeated {
+                    candidates.push(result);
+                }
+            }
+            Err(err) => last_error = Some(err),
+        }
+    }
+
+    if candidates
+        .iter()
+        .any(|candidate| is_usable(&candidate.message))
+    {
+        candidates.retain(|candidate| is_usable(&candidate.message));
+    }
+
+    match last_error {
+        Some(err) if candidates.is_empty() => Err(err),
+        _ if candidates.is_empty() => Err(anyhow::anyhow!("No candidate messages were generated")),
+        _ => Ok((candidates, usage)),
+    }
+}
+
+pub async fn generate_cancellable(
+    client: &dyn GenerateCommitMessage,
+    model: &Model,
+    fallbacks: &[(&dyn GenerateCommitMessage, &Model)],
+    retry: Option<(&dyn GenerateCommitMessage, &Model)>,
+    prompt: &Prompt,
+    retries: u32,
+    cancel: impl Future<Output = ()>,
+) -> Cancellable<anyhow::Result<GenerationResult>> {
+    run_cancellable(
+        generate_with_fallbacks(client, model, fallbacks, retry, prompt, retries),
+        cancel,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    struct FakeClient {
+        delay: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl GenerateCommitMessage for FakeClient {
+        async fn generate_commit_message(&self, _prompt: &Prompt) -> anyhow::Result<String> {
+            tokio::time::sleep(self.delay).await;
+            Ok("feat: add thing".to_string())
+        }
+    }
+
+    struct ScriptedClient {
+        responses: Mutex<Vec<&'static str>>,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedClient {
+        fn new(responses: &[&'static str]) -> Self {
+            Self {
+                responses: Mutex::new(responses.iter().rev().copied().collect()),
+                prompts: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl GenerateCommitMessage for ScriptedClient {
+        async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+            self.prompts.lock().unwrap().push(prompt.user.clone());
+            Ok(self.responses.lock().unwrap().pop().unwrap().to_string())
+        }
+    }
+
+    struct FailingClient;
+
+    #[async_trait::async_trait]
+    impl GenerateCommitMessage for FailingClient {
+        async fn generate_commit_message(&self, _prompt: &Prompt) -> anyhow::Result<String> {
+            Err(anyhow::anyhow!("HTTP error 529: overloaded"))
+        }
+    }
+
+    const MODEL: Model = Model::Haiku4_5;
+
+    fn test_prompt() -> Prompt {
+        Prompt {
+            system: "system".to_string(),
+            user: "user".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_phase_assembles_classification_and_description() {
+        let client =
+            ScriptedClient::new(&["refactor(cache):", "speed up eviction with an LRU list"]);
+
+        let result = generate_two_phase(&client, &MODEL, None, &test_prompt(), 0)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.message,
+            "refactor(cache): speed up eviction with an LRU list"
+        );
+        let prompts = client.prompts.lock().unwrap();
+        assert!(prompts[0].ends_with(CLASSIFY_REQUEST));
+        assert!(prompts[1].contains("classified as \"refactor(cache):\""));
+    }
+
+    #[tokio::test]
+    async fn test_two_phase_nudges_and_escalates_the_description() {
+        let client = ScriptedClient::new(&[
+            "fix(parser):",
+            "Here is a summary of the changes you made.",
+            "fix: handle empty input",
+        ]);
+        let result = generate_two_phase(&client, &MODEL, None, &test_prompt(), 1)
+            .await
+            .unwrap();
+        assert_eq!(result.message, "fix(parser): handle empty input");
+        assert!(client.prompts.lock().unwrap()[2].ends_with(FORMAT_NUDGE));
+
+        let primary = ScriptedClient::new(&["fix(parser):", "I updated the parser."]);
+        let retry = ScriptedClient::new(&["fix: handle empty input"]);
+        let result = generate_two_phase(
+            &primary,
+            &MODEL,
+            Some((&retry, &Model::Sonnet4_5)),
+            &test_prompt(),
+            0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.message, "fix(parser): handle empty input");
+        assert_eq!(result.model, Model::Sonnet4_5);
+    }
+
+    #[test]
+    fn test_two_phase_keeps_classification_over_reply() {
+        let classification = parse_classification("feat(api)!: ignored\n").unwrap();
+
+        assert_eq!(
+            assemble_two_phase(
+                &classification,
+                "fix: add cursor pagination\n\nPages hold 50 items."
+            ),
+            "feat(api)!: add cursor pagination\n\nPages hold 50 items."
+        );
+        assert!(parse_classification("I think this is a refactor").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_during_generation_skips_commit() {
+        let client = FakeClient {
+            delay: Duration::from_secs(60),
+        };
+        let mut commits = Vec::new();
+
+        let outcome =
+            generate_cancellable(&client, &MODEL, &[], None, &test_prompt(), 0, async {}).await;
+
+        assert!(matches!(outcome, Cancellable::Cancelled));
+        let committed = commit_if_completed(outcome, |result| {
+            commits.push(result.unwrap().message);
+        });
+        assert_eq!(committed, Cancellable::Cancelled);
+        assert!(commits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_completed_generation_commits() {
+        let client = FakeClient {
+            delay: Duration::from_millis(1),
+        };
+        let mut commits = Vec::new();
+
+        let outcome = generate_cancellable(
+            &client,
+            &MODEL,
+            &[],
+            None,
+            &test_prompt(),
+            0,
+            std::future::pending(),
+        )
+        .await;
+
+        let committed = commit_if_completed(outcome, |result| {
+            commits.push(result.unwrap().message);
+        });
+        assert_eq!(committed, Cancellable::Completed(()));
+        assert_eq!(commits, vec!["feat: add thing".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_short_response_is_retried_with_nudge() {
+        let client = ScriptedClient::new(&["", "feat(cli): add retry"]);
+
+        let message = generate_with_retry(&client, &MODEL, &test_prompt(), 1)
+            .await
+            .unwrap()
+            .message;
+
+        assert_eq!(message, "feat(cli): add retry");
+        let prompts = client.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 2);
+        assert_eq!(prompts[0], "user");
+        assert!(prompts[1].ends_with(SHORT_RESPONSE_NUDGE));
+    }
+
+    #[test]
+    fn test_placeholder_output_is_rejected() {
+        assert!(is_placeholder("type(scope): description"));
+        assert!(is_placeholder("`Type(Scope): Description`\n"));
+        assert!(is_placeholder("<type>: <description>\n\nbody"));
+        assert!(is_placeholder("merge: description"));
+        assert!(!is_usable("type(scope): description"));
+
+        assert!(!is_placeholder("fix(parser): handle empty input"));
+        assert!(is_usable("fix(parser): handle empty input"));
+    }
+
+    #[tokio::test]
+    async fn test_placeholder_response_is_retried() {
+        let client = ScriptedClient::new(&["type(scope): description", "feat: add thing"]);
+
+        let result = generate_with_retry(&client, &MODEL, &test_prompt(), 1)
+            .await
+            .unwrap();
+
+        assert_eq!(result.message, "feat: add thing");
+    }
+
+    #[tokio::test]
+    async fn test_retries_are_bounded() {
+        let client = ScriptedClient::new(&["", "fix", "docs: never asked"]);
+
+        let message = generate_with_retry(&client, &MODEL, &test_prompt(), 1)
+            .await
+            .unwrap()
+            .message;
+
+        assert_eq!(message, "fix");
+        assert_eq!(client.prompts.lock().unwrap().len(), 2);
+
+        let client = ScriptedClient::new(&["feat: add thing"]);
+        let message = generate_with_retry(&client, &MODEL, &test_prompt(), 1)
+            .await
+            .unwrap()
+            .message;
+        assert_eq!(message, "feat: add thing");
+    }
+
+    struct UsageClient;
+
+    #[async_trait::async_trait]
+    impl GenerateCommitMessage for UsageClient {
+        async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+            Ok(self.generate_with_usage(prompt).await?.0)
+        }
+
+        async fn generate_with_usage(
+            &self,
+            _prompt: &Prompt,
+        ) -> anyhow::Result<(String, Option<Usage>)> {
+            Ok((
+                "feat(api)!: add pagination".to_string(),
+                Some(Usage {
+                    input_tokens: 1200,
+                    output_tokens: 12,
+                }),
+            ))
+        }
+    }
+
+    // Reports the same token counts for every scripted reply
+    struct MeteredClient(ScriptedClient);
+
+    #[async_trait::async_trait]
+    impl GenerateCommitMessage for MeteredClient {
+        async fn generate_commit_message(&self, prompt: &Prompt) -> anyhow::Result<String> {
+            Ok(self.generate_with_usage(prompt).await?.0)
+        }
+
+        async fn generate_with_usage(
+            &self,
+            prompt: &Prompt,
+        ) -> anyhow::Result<(String, Option<Usage>)> {
+            let usage = Usage {
+                input_tokens: 100,
+                output_tokens: 10,
+            };
+            Ok((self.0.generate_commit_message(prompt).await?, Some(usage)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_adds_up_every_attempt() {
+        let usage = |requests: u32| {
+            Some(Usage {
+                input_tokens: 100 * requests,
+                output_tokens: 10 * requests,
+            })
+        };
+
+        let client = MeteredClient(ScriptedClient::new(&["ok", "feat: add thing"]));
+        let result = generate_with_retry(&client, &MODEL, &test_prompt(), 1)
+            .await
+            .unwrap();
+        assert_eq!(result.usage, usage(2));
+
+        let primary = MeteredClient(ScriptedClient::new(&["I updated things."]));
+        let retry = MeteredClient(ScriptedClient::new(&["feat: add thing"]));
+        let result = generate_with_escalation(
+            &primary,
+            &MODEL,
+            Some((&retry, &Model::Sonnet4_5)),
+            &test_prompt(),
+            0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.usage, usage(2));
+
+        let client = MeteredClient(ScriptedClient::new(&[
+            "feat: add thing",
+            "Here is a summary of the changes you made.",
+        ]));
+        let (candidates, total) = generate_candidates(&client, &MODEL, &test_prompt(), 2, 1, 0)
+            .await
+            .unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(total, usage(2));
+
+        let client = MeteredClient(ScriptedClient::new(&["feat: add thing"]));
+        let sink = |_: &str| {};
+        let result = generate(&Streaming::new(&client, &sink), &MODEL, &test_prompt())
+            .await
+            .unwrap();
+        assert_eq!(result.usage, usage(1));
+    }
+
+    #[tokio::test]
+    async fn test_result_carries_usage_and_parsed_commit() {
+        let result = generate(&UsageClient, &MODEL, &test_prompt())
+            .await
+            .unwrap();
+
+        assert_eq!(result.message, "feat(api)!: add pagination");
+        assert_eq!(result.model, MODEL);
+        assert_eq!(
+            result.usage,
+            Some(Usage {
+                input_tokens: 1200,
+                output_tokens: 12
+            })
+        );
+        let parsed = result.parsed.unwrap();
+        assert_eq!(parsed.commit_type, "feat");
+        assert_eq!(parsed.scope.as_deref(), Some("api"));
+        assert!(parsed.breaking);
+
+        let client = ScriptedClient::new(&["Updated some files"]);
+        let result = generate(&client, &MODEL, &test_prompt()).await.unwrap();
+        assert_eq!(result.usage, None);
+        assert_eq!(result.parsed, None);
+    }
+
+    struct CountingClient {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl GenerateCommitMessage for CountingClient {
+        async fn generate_commit_message(&self, _prompt: &Prompt) -> anyhow::Result<String> {
+            let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok("feat: add thing".to_string())
+        }
+    }
+
+    #[test]
+    fn test_small_context_truncates_more_than_large() {
+        let system = "x".repeat(4_000);
+        let diff = "+line\n".repeat(200_000);
+
+        let small = diff_byte_budget(Some(16_000), &system, Some(1024)).unwrap();
+        let large = diff_byte_budget(Some(200_000), &system, Some(1024)).unwrap();
+        assert_eq!(small, (16_000 - 1024 - 1_000 - PROMPT_OVERHEAD_TOKENS) * 4);
+        assert!(small < large);
+
+        let (small_diff, small_truncated) = crate::diff::truncate_diff(&diff, small);
+        let (large_diff, large_truncated) = crate::diff::truncate_diff(&diff, large);
+        assert!(small_truncated && large_truncated);
+        assert!(small_diff.len() < large_diff.len());
+
+        let (untouched, truncated) = crate::diff::truncate_diff(&diff, usize::MAX);
+        assert!(!truncated);
+        assert_eq!(untouched, diff);
+
+        assert_eq!(diff_byte_budget(None, &system, None), None);
+        assert_eq!(diff_byte_budget(Some(1_000), &system, None), Some(0));
+    }
+
+    #[test]
+    fn test_max_diff_tokens_caps_the_budget() {
+        assert_eq!(estimate_tokens("abcdefghi"), 3);
+        assert_eq!(cap_diff_budget(Some(400_000), Some(1_000)), Some(4_000));
+        assert_eq!(cap_diff_budget(Some(2_000), Some(1_000)), Some(2_000));
+        assert_eq!(cap_diff_budget(None, Some(1_000)), Some(4_000));
+        assert_eq!(cap_diff_budget(Some(2_000), None), Some(2_000));
+        assert_eq!(cap_diff_budget(None, None), None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_never_exceeds_limit() {
+        let client = CountingClient {
+            in_flight: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        };
+        let prompts = vec![test_prompt(); 10];
+
+        let results = generate_concurrently(&client, &MODEL, None, &prompts, 3, 0).await;
+
+        assert_eq!(results.len(), 10);
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(client.peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_retried() {
+        let client = ScriptedClient::new(&["ok", "feat: add thing"]);
+
+        let results = generate_concurrently(&client, &MODEL, None, &[test_prompt()], 1, 1).await;
+
+        assert_eq!(results[0].as_ref().unwrap().message, "feat: add thing");
+        assert!(client.prompts.lock().unwrap()[1].ends_with(SHORT_RESPONSE_NUDGE));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_retries_through_the_sink() {
+        let client = ScriptedClient::new(&["", "feat: add thing"]);
+        let tokens = Mutex::new(Vec::new());
+        let sink = |token: &str| tokens.lock().unwrap().push(token.to_string());
+        let streaming = Streaming::new(&client, &sink);
+
+        let result = generate_with_retry(&streaming, &MODEL, &test_prompt(), 1)
+            .await
+            .unwrap();
+
+        assert_eq!(result.message, "feat: add thing");
+        assert_eq!(*tokens.lock().unwrap(), vec!["", "feat: add thing"]);
+    }
+
+    #[tokio::test]
+    async fn test_generate_candidates_drops_repeats() {
+        let client = ScriptedClient::new(&[
+            "feat: add thing",
+            "feat: add thing\n",
+            "feat(cli): add thing",
+        ]);
+
+        let (candidates, _) = generate_candidates(&client, &MODEL, &test_prompt(), 3, 1, 0)
+            .await
+            .unwrap();
+
+        let messages: Vec<_> = candidates.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(messages, vec!["feat: add thing", "feat(cli): add thing"]);
+        assert_eq!(client.prompts.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_generate_candidates_retries_and_drops_unusable_replies() {
+        let client = ScriptedClient::new(&[
+            "ok",
+            "feat: add thing",
+            "Here is a summary of the changes you made.",
+        ]);
+
+        let (candidates, _) = generate_candidates(&client, &MODEL, &test_prompt(), 2, 1, 1)
+            .await
+            .unwrap();
+
+        let messages: Vec<_> = candidates.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(messages, vec!["feat: add thing"]);
+        assert_eq!(client.prompts.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_output_escalates_to_retry_model() {
+        let primary = ScriptedClient::new(&[
+            "Here is a summary of the changes you made.",
+            "I updated the parser to handle empty input.",
+        ]);
+        let retry = ScriptedClient::new(&["fix(parser): handle empty input"]);
+
+        let result = generate_with_escalation(
+            &primary,
+            &MODEL,
+            Some((&retry, &Model::Sonnet4_5)),
+            &test_prompt(),
+            1,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.message, "fix(parser): handle empty input");
+        assert_eq!(result.model, Model::Sonnet4_5);
+        let primary_prompts = primary.prompts.lock().unwrap();
+        assert_eq!(primary_prompts.len(), 2);
+        assert!(primary_prompts[1].ends_with(FORMAT_NUDGE));
+        assert_eq!(retry.prompts.lock().unwrap().as_slice(), ["user"]);
+    }
+
+    #[tokio::test]
+    async fn test_escalation_fails_when_retry_model_is_invalid_too() {
+        let primary = ScriptedClient::new(&["Here is a summary.", "Still a summary."]);
+        let retry = ScriptedClient::new(&["Another summary.", "Yet another summary."]);
+
+        let error = generate_with_escalation(
+            &primary,
+            &MODEL,
+            Some((&retry, &Model::Sonnet4_5)),
+            &test_prompt(),
+            1,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert!(error.contains("Yet another summary."));
+        assert_eq!(primary.prompts.lock().unwrap().len(), 2);
+        assert_eq!(retry.prompts.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_valid_output_skips_retry_model() {
+        let primary = ScriptedClient::new(&["feat: add thing"]);
+        let retry = ScriptedClient::new(&[]);
+
+        let result = generate_with_escalation(
+            &primary,
+            &MODEL,
+            Some((&retry, &Model::Sonnet4_5)),
+            &test_prompt(),
+            1,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.message, "feat: add thing");
+        assert_eq!(result.model, MODEL);
+        assert!(retry.prompts.lock().unwrap().is_empty());
+
+        let primary = ScriptedClient::new(&["Updated things"]);
+        let result = generate_with_escalation(&primary, &MODEL, None, &test_prompt(), 0)
+            .await
+            .unwrap();
+        assert_eq!(result.message, "Updated things");
+    }
+
+    #[test]
+    fn test_api_id_names_the_model_that_wrote_the_message() {
+        let result = GenerationResult::new("feat: add thing".to_string(), MODEL, None);
+        assert_eq!(result.api_id(&MODEL, None), MODEL.to_api_str());
+        assert_eq!(
+            result.api_id(&MODEL, Some("qwen2.5-coder")),
+            "qwen2.5-coder"
+        );
+
+        let escalated =
+            GenerationResult::new("feat: add thing".to_string(), Model::Sonnet4_5, None);
+        assert_eq!(
+            escalated.api_id(&MODEL, Some("qwen2.5-coder")),
+            Model::Sonnet4_5.to_api_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_model_falls_back_to_next_in_chain() {
+        let fallback = ScriptedClient::new(&["feat: add thing"]);
+        let unused = ScriptedClient::new(&[]);
+
+        let result = generate_with_fallbacks(
+            &FailingClient,
+            &MODEL,
+            &[(&fallback, &Model::Gpt5Mini), (&unused, &Model::Sonnet4)],
+            None,
+            &test_prompt(),
+            0,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.message, "feat: add thing");
+        assert_eq!(result.model, Model::Gpt5Mini);
+        assert!(unused.prompts.lock().unwrap().is_empty());
+
+        let error = generate_with_fallbacks(
+            &FailingClient,
+            &MODEL,
+            &[(&FailingClient, &Model::Gpt5Mini)],
+            None,
+            &test_prompt(),
+            0,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("overloaded"));
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_fallback_only_fails_when_reached() {
+        let unavailable =
+            Unavailable::new(anyhow::anyhow!("OpenAI API key required for gpt5-mini"));
+
+        let result = generate_with_fallbacks(
+            &ScriptedClient::new(&["feat: add thing"]),
+            &MODEL,
+            &[(&unavailable, &Model::Gpt5Mini)],
+            None,
+            &test_prompt(),
+            0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.model, MODEL);
+
+        let error = generate_with_fallbacks(
+            &FailingClient,
+            &MODEL,
+            &[(&unavailable, &Model::Gpt5Mini)],
+            None,
+            &test_prompt(),
+            0,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("OpenAI API key required"));
+    }
+}
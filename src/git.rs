@@ -1,12 +1,122 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
 use std::process::Command;
+use tempfile::NamedTempFile;
+
+const SYMBOLIC_REF_ARGS: [&str; 3] = ["symbolic-ref", "-q", "HEAD"];
+const LATEST_TAG_ARGS: [&str; 3] = ["describe", "--tags", "--abbrev=0"];
+
+// Well-known hash of git's empty tree, used as the diff base for root commits
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub function_context: bool,
+    pub find_renames: Option<u8>,
+    pub find_copies: bool,
+}
+
+impl DiffOptions {
+    fn flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if self.function_context {
+            flags.push("-W".to_string());
+        }
+        if let Some(threshold) = self.find_renames {
+            flags.push(format!("-M{threshold}%"));
+        }
+        if self.find_copies {
+            flags.push("-C".to_string());
+        }
+        flags
+    }
+
+    pub fn detects_renames(&self) -> bool {
+        self.find_renames.is_some() || self.find_copies
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitMethod {
+    // Passes the message through a temporary file with -F
+    #[default]
+    File,
+    // Passes each paragraph as its own -m
+    Message,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommitOverrides {
+    pub date: Option<String>,
+    pub allow_empty: bool,
+    pub method: CommitMethod,
+    // Commits only these paths and leaves the rest of the index staged
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeState {
+    pub branch: Option<String>,
+    pub conflicts: Vec<String>,
+}
+
+impl MergeState {
+    // git prefills MERGE_MSG with "Merge branch 'x'" and a commented "# Conflicts:" list
+    pub fn parse(merge_msg: &str) -> Self {
+        let branch = merge_msg.lines().next().and_then(|subject| {
+            let (_, rest) = subject.split_once('\'')?;
+            let (branch, _) = rest.split_once('\'')?;
+            Some(branch.to_string())
+        });
+
+        let conflicts = merge_msg
+            .lines()
+            .skip_while(|line| line.trim_start_matches('#').trim() != "Conflicts:")
+            .skip(1)
+            .map_while(|line| line.strip_prefix("#\t").or_else(|| line.strip_prefix('\t')))
+            .map(|path| path.trim().to_string())
+            .collect();
+
+        Self { branch, conflicts }
+    }
+
+    pub fn context(&self) -> String {
+        let mut context = match &self.branch {
+            Some(branch) => format!("Merging branch: {branch}"),
+            None => "Merging a branch".to_string(),
+        };
+        if !self.conflicts.is_empty() {
+            context.push_str("\nResolved conflicts in:");
+            for path in &self.conflicts {
+                context.push_str(&format!("\n- {path}"));
+            }
+        }
+        context
+    }
+}
 
 pub struct Git;
 
 impl Git {
     pub fn get_staged_files() -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let output = Command::new("git")
-            .args(["diff", "--cached", "--name-only"])
-            .output()?;
+        Self::get_changed_files(None)
+    }
+
+    pub fn get_staged_files_since(base: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Self::get_changed_files(Some(base))
+    }
+
+    fn get_changed_files(base: Option<&str>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut command = Command::new("git");
+        command.args(["diff", "--cached", "--name-only"]);
+        if let Some(base) = base {
+            command.arg(base);
+        }
+
+        let output = command.output()?;
 
         if !output.status.success() {
             return Err("Failed to get staged files".into());
@@ -21,29 +131,503 @@ impl Git {
         Ok(files)
     }
 
+    // Files whose working tree copy differs from the staged one
+    pub fn unstaged_files(files: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Self::unstaged_files_in(Path::new("."), files)
+    }
+
+    fn unstaged_files_in(
+        dir: &Path,
+        files: &[String],
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(["diff", "--name-only", "--"])
+            .args(files)
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to get unstaged files: {error}").into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect())
+    }
+
     pub fn get_staged_diff(files: &[String]) -> Result<String, Box<dyn std::error::Error>> {
-        let mut command = Command::new("git");
-        command.args(["diff", "--cached"]);
+        Self::get_diff(None, files, &DiffOptions::default())
+    }
+
+    pub fn get_staged_diff_since(
+        base: &str,
+        files: &[String],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        Self::get_diff(Some(base), files, &DiffOptions::default())
+    }
+
+    pub fn get_diff(
+        base: Option<&str>,
+        files: &[String],
+        options: &DiffOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(Self::diff_args(base, &options.flags(), files))
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to get staged diff".into());
+        }
+
+        let (diff, lossy) = Self::decode_diff(output.stdout);
+        if lossy {
+            crate::output::warning(
+                "Staged diff contains non-UTF-8 content, invalid bytes were replaced",
+            );
+        }
+
+        Ok(diff)
+    }
+
+    // A single latin-1 file shouldn't abort the whole run, so replace invalid bytes instead
+    fn decode_diff(bytes: Vec<u8>) -> (String, bool) {
+        match String::from_utf8(bytes) {
+            Ok(diff) => (diff, false),
+            Err(err) => (String::from_utf8_lossy(err.as_bytes()).into_owned(), true),
+        }
+    }
+
+    pub fn get_staged_status(
+        base: Option<&str>,
+        files: &[String],
+        options: &DiffOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(Self::status_args(base, options, files))
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to get staged status".into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    pub fn get_staged_numstat(
+        base: Option<&str>,
+        files: &[String],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(Self::diff_args(base, &["--numstat".to_string()], files))
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to get staged numstat".into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    // NUL-separated, so a rename lists both paths instead of "dir/{old => new}"
+    pub fn get_staged_numstat_z(
+        base: Option<&str>,
+        files: &[String],
+        options: &DiffOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(Self::numstat_z_args(base, options, files))
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to get staged numstat".into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    fn numstat_z_args(base: Option<&str>, options: &DiffOptions, files: &[String]) -> Vec<String> {
+        let mut flags = vec!["--numstat".to_string(), "-z".to_string()];
+        flags.extend(options.flags());
+        Self::diff_args(base, &flags, files)
+    }
+
+    pub fn is_whitespace_only(
+        base: Option<&str>,
+        files: &[String],
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(Self::diff_args(base, &["-w".to_string()], files))
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to get staged diff ignoring whitespace".into());
+        }
+
+        Ok(Self::is_blank_diff(&output.stdout))
+    }
+
+    // With -w, git drops files whose only changes are whitespace, headers included
+    fn is_blank_diff(output: &[u8]) -> bool {
+        output.iter().all(u8::is_ascii_whitespace)
+    }
+
+    fn status_args(base: Option<&str>, options: &DiffOptions, files: &[String]) -> Vec<String> {
+        let mut flags = vec!["--name-status".to_string()];
+        flags.extend(options.flags());
+        Self::diff_args(base, &flags, files)
+    }
+
+    pub fn diff_stat(
+        base: Option<&str>,
+        files: &[String],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(Self::diff_args(base, &["--stat".to_string()], files))
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to get staged diff stat".into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    fn diff_args(base: Option<&str>, flags: &[String], files: &[String]) -> Vec<String> {
+        let mut args = vec!["diff".to_string(), "--cached".to_string()];
+        args.extend(flags.iter().cloned());
+
+        if let Some(base) = base {
+            args.push(base.to_string());
+        }
 
         if !files.is_empty() {
-            command.arg("--");
-            for file in files {
-                command.arg(file);
+            args.push("--".to_string());
+            args.extend(files.iter().cloned());
+        }
+
+        args
+    }
+
+    pub fn repo_root() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--show-toplevel"])
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to locate the repository root".into());
+        }
+
+        Ok(PathBuf::from(String::from_utf8(output.stdout)?.trim_end()))
+    }
+
+    pub fn current_branch() -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(["branch", "--show-current"])
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to read the current branch".into());
+        }
+
+        let branch = String::from_utf8(output.stdout)?.trim().to_string();
+        Ok((!branch.is_empty()).then_some(branch))
+    }
+
+    pub fn recent_subjects(count: usize) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Self::recent_subjects_by(count, None)
+    }
+
+    pub fn recent_subjects_by(
+        count: usize,
+        author_email: Option<&str>,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(Self::recent_subjects_args(count, author_email))
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to read recent commits".into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect())
+    }
+
+    // --author is a pattern over "Name <email>", so match the bracketed email literally
+    fn recent_subjects_args(count: usize, author_email: Option<&str>) -> Vec<String> {
+        let mut args = vec![
+            "log".to_string(),
+            "-n".to_string(),
+            count.to_string(),
+            "--format=%s".to_string(),
+        ];
+        if let Some(email) = author_email {
+            args.push("--fixed-strings".to_string());
+            args.push(format!("--author=<{email}>"));
+        }
+        args
+    }
+
+    pub fn user_email() -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(["config", "user.email"])
+            .output()?;
+
+        // Exits 1 when the key isn't set
+        let email = String::from_utf8(output.stdout)?.trim().to_string();
+        Ok((output.status.success() && !email.is_empty()).then_some(email))
+    }
+
+    pub fn head_message() -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(["log", "-1", "--format=%B"])
+            .output()?;
+
+        if !output.status.success() {
+            return Err("Failed to read the HEAD commit message".into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?.trim_end().to_string())
+    }
+
+    pub fn head_sha() -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git").args(["rev-parse", "HEAD"]).output()?;
+
+        if !output.status.success() {
+            return Err("Failed to resolve HEAD".into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    }
+
+    pub fn amend_base() -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--verify", "--quiet", "HEAD~1"])
+            .output()?;
+
+        if output.status.success() {
+            Ok("HEAD~1".to_string())
+        } else {
+            Ok(EMPTY_TREE.to_string())
+        }
+    }
+
+    pub fn latest_tag() -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let output = Command::new("git").args(LATEST_TAG_ARGS).output()?;
+
+        Self::parse_latest_tag(output.status.success(), &output.stdout, &output.stderr)
+    }
+
+    fn parse_latest_tag(
+        success: bool,
+        stdout: &[u8],
+        stderr: &[u8],
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        if success {
+            let tag = String::from_utf8(stdout.to_vec())?.trim().to_string();
+            return Ok((!tag.is_empty()).then_some(tag));
+        }
+
+        let error = String::from_utf8_lossy(stderr);
+        if error.contains("No names found") || error.contains("No tags can describe") {
+            Ok(None)
+        } else {
+            Err(format!("Failed to find the latest tag: {error}").into())
+        }
+    }
+
+    pub fn merge_state() -> Result<Option<MergeState>, Box<dyn std::error::Error>> {
+        let merging = Command::new("git")
+            .args(["rev-parse", "--verify", "--quiet", "MERGE_HEAD"])
+            .output()?
+            .status
+            .success();
+        if !merging {
+            return Ok(None);
+        }
+
+        let output = Command::new("git")
+            .args(["rev-parse", "--git-path", "MERGE_MSG"])
+            .output()?;
+        if !output.status.success() {
+            return Err("Failed to locate MERGE_MSG".into());
+        }
+
+        let path = PathBuf::from(String::from_utf8(output.stdout)?.trim_end());
+        let merge_msg = std::fs::read_to_string(path).unwrap_or_default();
+        Ok(Some(MergeState::parse(&merge_msg)))
+    }
+
+    pub fn is_detached_head() -> Result<bool, Box<dyn std::error::Error>> {
+        Self::is_detached_head_in(Path::new("."))
+    }
+
+    fn is_detached_head_in(dir: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(SYMBOLIC_REF_ARGS)
+            .output()?;
+
+        // Exit status 1 means HEAD is not a symbolic ref, anything else is a real error
+        match output.status.code() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => {
+                let error = String::from_utf8_lossy(&output.stderr);
+                Err(format!("Failed to inspect HEAD: {error}").into())
             }
         }
+    }
 
-        let output = command.output()?;
+    pub fn index_differs_from_head() -> Result<bool, Box<dyn std::error::Error>> {
+        Self::index_differs_from_head_in(Path::new("."))
+    }
+
+    fn index_differs_from_head_in(dir: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(["diff", "--cached", "--quiet", "HEAD"])
+            .output()?;
+
+        match output.status.code() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => {
+                let error = String::from_utf8_lossy(&output.stderr);
+                Err(format!("Failed to compare the index with HEAD: {error}").into())
+            }
+        }
+    }
+
+    pub fn branch_exists(name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args([
+                "show-ref",
+                "--verify",
+                "--quiet",
+                &format!("refs/heads/{name}"),
+            ])
+            .output()?;
+
+        match output.status.code() {
+            Some(0) => Ok(true),
+            Some(1) => Ok(false),
+            _ => {
+                let error = String::from_utf8_lossy(&output.stderr);
+                Err(format!("Failed to look up branch {name}: {error}").into())
+            }
+        }
+    }
+
+    pub fn create_branch(name: &str, reset: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if !reset && Self::branch_exists(name)? {
+            return Err(format!(
+                "Branch {name} already exists. Pick another name or pass --reset-branch to reset it"
+            )
+            .into());
+        }
+
+        let output = Command::new("git")
+            .args(Self::create_branch_args(name, reset))
+            .output()?;
 
         if !output.status.success() {
-            return Err("Failed to get staged diff".into());
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to create branch {name}: {error}").into());
         }
 
-        Ok(String::from_utf8(output.stdout)?)
+        Ok(())
+    }
+
+    fn create_branch_args(name: &str, reset: bool) -> Vec<String> {
+        vec![
+            "switch".to_string(),
+            if reset { "-C" } else { "-c" }.to_string(),
+            name.to_string(),
+        ]
+    }
+
+    pub fn guard_detached_head(
+        detached: bool,
+        force: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if detached && !force {
+            return Err(
+                "HEAD is detached, so the commit would not belong to any branch. \
+                 Create one with `git switch -c <name>` first, or pass --force to commit anyway"
+                    .into(),
+            );
+        }
+
+        Ok(())
+    }
+
+    // Skips the commit when HEAD already carries the message and the index has nothing
+    // new on top of it, e.g. after a re-run following a crash that happened once the
+    // commit had been created. Staged work is always committed, even if the generated
+    // message repeats the previous one
+    pub fn commit_once<F>(
+        head_message: Option<&str>,
+        index_differs: bool,
+        message: &str,
+        commit: F,
+    ) -> Result<bool, Box<dyn std::error::Error>>
+    where
+        F: FnOnce(&str) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        if !index_differs && head_message.is_some_and(|head| head.trim() == message.trim()) {
+            return Ok(false);
+        }
+
+        commit(message)?;
+        Ok(true)
+    }
+
+    pub fn add_note(target: &str, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(Self::note_args(target, message))
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to add note to {target}: {error}").into());
+        }
+
+        Ok(())
+    }
+
+    fn note_args(target: &str, message: &str) -> Vec<String> {
+        vec![
+            "notes".to_string(),
+            "add".to_string(),
+            "-m".to_string(),
+            message.to_string(),
+            target.to_string(),
+        ]
     }
 
     pub fn commit(message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Self::commit_with(message, &CommitOverrides::default())
+    }
+
+    pub fn commit_with(
+        message: &str,
+        overrides: &CommitOverrides,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let message_file = Self::message_file(message, overrides.method)?;
         let output = Command::new("git")
-            .args(["commit", "-m", message])
+            .args(Self::commit_args(
+                message,
+                false,
+                overrides,
+                message_file.as_ref().map(NamedTempFile::path),
+            ))
             .output()?;
 
         if !output.status.success() {
@@ -53,4 +637,486 @@ impl Git {
 
         Ok(())
     }
+
+    pub fn amend(message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Self::amend_with(message, &CommitOverrides::default())
+    }
+
+    pub fn amend_with(
+        message: &str,
+        overrides: &CommitOverrides,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let message_file = Self::message_file(message, overrides.method)?;
+        let output = Command::new("git")
+            .args(Self::commit_args(
+                message,
+                true,
+                overrides,
+                message_file.as_ref().map(NamedTempFile::path),
+            ))
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to amend commit: {error}").into());
+        }
+
+        Ok(())
+    }
+
+    fn message_file(
+        message: &str,
+        method: CommitMethod,
+    ) -> Result<Option<NamedTempFile>, Box<dyn std::error::Error>> {
+        if method != CommitMethod::File {
+            return Ok(None);
+        }
+
+        let mut file = NamedTempFile::new()?;
+        file.write_all(message.as_bytes())?;
+        file.flush()?;
+        Ok(Some(file))
+    }
+
+    fn commit_args(
+        message: &str,
+        amend: bool,
+        overrides: &CommitOverrides,
+        message_file: Option<&Path>,
+    ) -> Vec<String> {
+        let mut args = vec!["commit".to_string()];
+        if amend {
+            args.push("--amend".to_string());
+        }
+        if let Some(date) = &overrides.date {
+            args.push(format!("--date={date}"));
+        }
+        if overrides.allow_empty {
+            args.push("--allow-empty".to_string());
+        }
+        match message_file {
+            Some(path) => {
+                args.push("-F".to_string());
+                args.push(path.display().to_string());
+            }
+            // git joins repeated -m values with a blank line, so paragraphs round-trip
+            None => {
+                for paragraph in message.trim().split("\n\n") {
+                    let paragraph = paragraph.trim_matches('\n');
+                    if !paragraph.is_empty() {
+                        args.push("-m".to_string());
+                        args.push(paragraph.to_string());
+                    }
+                }
+            }
+        }
+        if !overrides.paths.is_empty() {
+            args.push("--".to_string());
+            args.extend(overrides.paths.iter().cloned());
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_state_parses_branch_and_conflicts() {
+        let state = MergeState::parse(
+            "Merge branch 'feature/oauth' into main\n\n# Conflicts:\n#\tsrc/auth.rs\n#\tsrc/config.rs\n#\n# It looks like you may be committing a merge.\n",
+        );
+
+        assert_eq!(state.branch.as_deref(), Some("feature/oauth"));
+        assert_eq!(state.conflicts, vec!["src/auth.rs", "src/config.rs"]);
+        assert_eq!(
+            state.context(),
+            "Merging branch: feature/oauth\nResolved conflicts in:\n- src/auth.rs\n- src/config.rs"
+        );
+
+        let clean = MergeState::parse("Merge remote-tracking branch 'origin/main'\n");
+        assert_eq!(clean.branch.as_deref(), Some("origin/main"));
+        assert!(clean.conflicts.is_empty());
+    }
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .current_dir(dir)
+            .args([
+                "-c",
+                "user.name=convmit",
+                "-c",
+                "user.email=convmit@example.com",
+            ])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    fn temp_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        run_git(dir.path(), &["init", "-q"]);
+        std::fs::write(dir.path().join("README.md"), "hello\n").unwrap();
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-q", "-m", "feat: add readme"]);
+        dir
+    }
+
+    #[test]
+    fn test_detached_head_detection() {
+        let repo = temp_repo();
+        assert!(!Git::is_detached_head_in(repo.path()).unwrap());
+
+        run_git(repo.path(), &["checkout", "-q", "--detach"]);
+        assert!(Git::is_detached_head_in(repo.path()).unwrap());
+    }
+
+    #[test]
+    fn test_recent_subjects_args_filter_by_author() {
+        assert_eq!(
+            Git::recent_subjects_args(20, None),
+            ["log", "-n", "20", "--format=%s"]
+        );
+        assert_eq!(
+            Git::recent_subjects_args(20, Some("dev+ci@example.com")),
+            [
+                "log",
+                "-n",
+                "20",
+                "--format=%s",
+                "--fixed-strings",
+                "--author=<dev+ci@example.com>"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_latest_tag() {
+        assert_eq!(
+            Git::parse_latest_tag(true, b"v1.2.0\n", b"").unwrap(),
+            Some("v1.2.0".to_string())
+        );
+        assert_eq!(
+            Git::parse_latest_tag(
+                false,
+                b"",
+                b"fatal: No names found, cannot describe anything.\n"
+            )
+            .unwrap(),
+            None
+        );
+        assert!(Git::parse_latest_tag(false, b"", b"fatal: not a git repository\n").is_err());
+    }
+
+    #[test]
+    fn test_guard_detached_head() {
+        let error = Git::guard_detached_head(true, false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("HEAD is detached"));
+        assert!(error.contains("git switch -c"));
+        assert!(error.contains("--force"));
+
+        assert!(Git::guard_detached_head(true, true).is_ok());
+        assert!(Git::guard_detached_head(false, false).is_ok());
+    }
+
+    #[test]
+    fn test_function_context_adds_w_flag() {
+        let files = vec!["src/main.rs".to_string()];
+        let options = DiffOptions {
+            function_context: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Git::diff_args(None, &options.flags(), &files),
+            vec!["diff", "--cached", "-W", "--", "src/main.rs"]
+        );
+        assert_eq!(
+            Git::diff_args(None, &DiffOptions::default().flags(), &files),
+            vec!["diff", "--cached", "--", "src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_commit_once_skips_identical_head_message() {
+        let mut commits = Vec::new();
+
+        let committed = Git::commit_once(
+            Some("feat: add thing\n"),
+            false,
+            "feat: add thing",
+            |message| {
+                commits.push(message.to_string());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(!committed);
+        assert!(commits.is_empty());
+    }
+
+    #[test]
+    fn test_commit_once_commits_staged_work_with_repeated_message() {
+        let repo = temp_repo();
+        std::fs::write(repo.path().join("README.md"), "hello again\n").unwrap();
+        run_git(repo.path(), &["add", "README.md"]);
+        let index_differs = Git::index_differs_from_head_in(repo.path()).unwrap();
+        assert!(index_differs);
+
+        let mut commits = Vec::new();
+        let committed = Git::commit_once(
+            Some("feat: add readme"),
+            index_differs,
+            "feat: add readme",
+            |message| {
+                commits.push(message.to_string());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(committed);
+        assert_eq!(commits, vec!["feat: add readme".to_string()]);
+
+        run_git(repo.path(), &["commit", "-q", "-m", "feat: add readme"]);
+        assert!(!Git::index_differs_from_head_in(repo.path()).unwrap());
+    }
+
+    #[test]
+    fn test_commit_once_commits_new_message() {
+        let mut commits = Vec::new();
+
+        let committed = Git::commit_once(
+            Some("fix: old thing"),
+            false,
+            "feat: add thing",
+            |message| {
+                commits.push(message.to_string());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(committed);
+        assert_eq!(commits, vec!["feat: add thing".to_string()]);
+        assert!(Git::commit_once(None, false, "feat: add thing", |_| Ok(())).unwrap());
+    }
+
+    #[test]
+    fn test_find_renames_threshold_args() {
+        let options = DiffOptions {
+            find_renames: Some(70),
+            find_copies: true,
+            ..Default::default()
+        };
+
+        assert_eq!(options.flags(), vec!["-M70%", "-C"]);
+        assert_eq!(
+            Git::status_args(None, &options, &[]),
+            vec!["diff", "--cached", "--name-status", "-M70%", "-C"]
+        );
+        assert!(options.detects_renames());
+        assert!(!DiffOptions::default().detects_renames());
+    }
+
+    #[test]
+    fn test_commit_args_allow_empty() {
+        let overrides = CommitOverrides {
+            allow_empty: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Git::commit_args("chore: mark 2.0 feature freeze", false, &overrides, None),
+            vec![
+                "commit",
+                "--allow-empty",
+                "-m",
+                "chore: mark 2.0 feature freeze"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commit_args_for_each_method() {
+        let message =
+            "feat(git): pick the commit method\n\nSome CI runners mangle -m.\n\nRefs: #985\n";
+
+        let overrides = CommitOverrides {
+            method: CommitMethod::Message,
+            ..Default::default()
+        };
+        let message_file = Git::message_file(message, overrides.method).unwrap();
+        assert!(message_file.is_none());
+        assert_eq!(
+            Git::commit_args(message, false, &overrides, None),
+            vec![
+                "commit",
+                "-m",
+                "feat(git): pick the commit method",
+                "-m",
+                "Some CI runners mangle -m.",
+                "-m",
+                "Refs: #985"
+            ]
+        );
+
+        let overrides = CommitOverrides::default();
+        let message_file = Git::message_file(message, overrides.method)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(message_file.path()).unwrap(),
+            message
+        );
+        assert_eq!(
+            Git::commit_args(message, false, &overrides, Some(message_file.path())),
+            vec![
+                "commit".to_string(),
+                "-F".to_string(),
+                message_file.path().display().to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_branch_args() {
+        assert_eq!(
+            Git::create_branch_args("feat/pagination", false),
+            vec!["switch", "-c", "feat/pagination"]
+        );
+        assert_eq!(
+            Git::create_branch_args("feat/pagination", true),
+            vec!["switch", "-C", "feat/pagination"]
+        );
+    }
+
+    #[test]
+    fn test_note_args() {
+        assert_eq!(
+            Git::note_args("HEAD", "feat: add notes\n\nWith a body."),
+            vec![
+                "notes",
+                "add",
+                "-m",
+                "feat: add notes\n\nWith a body.",
+                "HEAD"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commit_args_with_date() {
+        let overrides = CommitOverrides {
+            date: Some("2024-03-01 12:00:00 +0100".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Git::commit_args("feat: add thing", false, &overrides, None),
+            vec![
+                "commit",
+                "--date=2024-03-01 12:00:00 +0100",
+                "-m",
+                "feat: add thing"
+            ]
+        );
+        assert_eq!(
+            Git::commit_args("feat: add thing", true, &CommitOverrides::default(), None),
+            vec!["commit", "--amend", "-m", "feat: add thing"]
+        );
+    }
+
+    #[test]
+    fn test_commit_args_limited_to_paths() {
+        let overrides = CommitOverrides {
+            paths: vec!["src/ai.rs".to_string(), "--weird name".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Git::commit_args("feat: add thing", false, &overrides, None),
+            vec![
+                "commit",
+                "-m",
+                "feat: add thing",
+                "--",
+                "src/ai.rs",
+                "--weird name"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unstaged_files_lists_files_edited_after_staging() {
+        let repo = temp_repo();
+        std::fs::write(repo.path().join("README.md"), "staged\n").unwrap();
+        std::fs::write(repo.path().join("notes.md"), "staged\n").unwrap();
+        run_git(repo.path(), &["add", "README.md", "notes.md"]);
+        std::fs::write(repo.path().join("notes.md"), "edited after staging\n").unwrap();
+
+        let files = vec!["README.md".to_string(), "notes.md".to_string()];
+        assert_eq!(
+            Git::unstaged_files_in(repo.path(), &files).unwrap(),
+            vec!["notes.md"]
+        );
+    }
+
+    #[test]
+    fn test_decode_diff_degrades_on_non_utf8() {
+        let bytes = b"+let name = \"caf\xe9\";\n".to_vec();
+        assert!(String::from_utf8(bytes.clone()).is_err());
+
+        let (diff, lossy) = Git::decode_diff(bytes);
+        assert!(lossy);
+        assert_eq!(diff, "+let name = \"caf\u{FFFD}\";\n");
+
+        let (diff, lossy) = Git::decode_diff(b"+plain\n".to_vec());
+        assert!(!lossy);
+        assert_eq!(diff, "+plain\n");
+    }
+
+    #[test]
+    fn test_whitespace_only_detection() {
+        assert_eq!(
+            Git::diff_args(None, &["-w".to_string()], &["src/ai.rs".to_string()]),
+            vec!["diff", "--cached", "-w", "--", "src/ai.rs"]
+        );
+
+        assert!(Git::is_blank_diff(b""));
+        assert!(Git::is_blank_diff(b"\n"));
+        assert!(!Git::is_blank_diff(
+            b"diff --git a/empty b/empty\nnew file mode 100644\n"
+        ));
+        assert!(!Git::is_blank_diff(
+            b"diff --git a/f b/f\n@@ -1 +1 @@\n-a\n+b\n"
+        ));
+    }
+
+    #[test]
+    fn test_diff_stat_args() {
+        let files = vec!["src/main.rs".to_string(), "README.md".to_string()];
+
+        assert_eq!(
+            Git::diff_args(None, &["--stat".to_string()], &files),
+            vec![
+                "diff",
+                "--cached",
+                "--stat",
+                "--",
+                "src/main.rs",
+                "README.md"
+            ]
+        );
+        assert_eq!(
+            Git::diff_args(Some("HEAD~1"), &["--stat".to_string()], &[]),
+            vec!["diff", "--cached", "--stat", "HEAD~1"]
+        );
+    }
 }
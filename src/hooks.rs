@@ -0,0 +1,267 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+pub const MESSAGE_ENV_VAR: &str = "CONVMIT_MESSAGE";
+pub const SHA_ENV_VAR: &str = "CONVMIT_SHA";
+pub const COMMITLINT_COMMAND: &str = "commitlint";
+
+// Exit codes shells use when the command itself could not be found
+const COMMAND_NOT_FOUND_CODES: [i32; 2] = [127, 9009];
+
+#[derive(Debug, PartialEq)]
+pub enum LintOutcome {
+    Passed,
+    Failed(String),
+    Unavailable,
+}
+
+pub fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+fn combined_output(output: &Output) -> String {
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    text.trim_end().to_string()
+}
+
+pub fn run_pre_commit_validation(command: &str, message: &str) -> anyhow::Result<()> {
+    let output = shell_command(command)
+        .env(MESSAGE_ENV_VAR, message)
+        .output()
+        .map_err(|err| anyhow::anyhow!("Failed to run validation command `{command}`: {err}"))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    Err(anyhow::anyhow!(
+        "Validation command `{}` failed ({}), not committing:\n{}",
+        command,
+        output.status,
+        combined_output(&output)
+    ))
+}
+
+pub fn run_context_command(command: &str) -> anyhow::Result<String> {
+    let output = shell_command(command)
+        .output()
+        .map_err(|err| anyhow::anyhow!("Failed to run context command `{command}`: {err}"))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Context command `{}` failed ({}):\n{}",
+            command,
+            output.status,
+            combined_output(&output)
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+// Output is left attached to the terminal so things like `git push` can show progress
+pub fn run_post_commit_command(command: &str, sha: &str, message: &str) -> anyhow::Result<()> {
+    let status = shell_command(command)
+        .env(SHA_ENV_VAR, sha)
+        .env(MESSAGE_ENV_VAR, message)
+        .status()
+        .map_err(|err| anyhow::anyhow!("Failed to run post-commit command `{command}`: {err}"))?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "Post-commit command `{command}` failed ({status})"
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn run_commitlint(message: &str) -> anyhow::Result<LintOutcome> {
+    run_lint_command(COMMITLINT_COMMAND, message)
+}
+
+fn run_lint_command(command: &str, message: &str) -> anyhow::Result<LintOutcome> {
+    let mut child = match shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(LintOutcome::Unavailable);
+        }
+        Err(err) => return Err(anyhow::anyhow!("Failed to run `{command}`: {err}")),
+    };
+
+    // A linter that exits without reading stdin closes the pipe early, which is not an error
+    if let Some(mut stdin) = child.stdin.take()
+        && let Err(err) = stdin.write_all(message.as_bytes())
+        && err.kind() != std::io::ErrorKind::BrokenPipe
+    {
+        return Err(err.into());
+    }
+    let output = child.wait_with_output()?;
+
+    if output.status.success() {
+        return Ok(LintOutcome::Passed);
+    }
+
+    match output.status.code() {
+        Some(code) if COMMAND_NOT_FOUND_CODES.contains(&code) => Ok(LintOutcome::Unavailable),
+        _ => Ok(LintOutcome::Failed(combined_output(&output))),
+    }
+}
+
+pub fn commit_with_validation<F>(
+    validate_command: Option<&str>,
+    message: &str,
+    commit: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnOnce(&str) -> Result<(), Box<dyn std::error::Error>>,
+{
+    if let Some(command) = validate_command {
+        run_pre_commit_validation(command, message)?;
+    }
+
+    commit(message)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failing_validator_prevents_commit() {
+        let mut committed = false;
+
+        let result = commit_with_validation(
+            Some("echo \"rejected: $CONVMIT_MESSAGE\"; exit 1"),
+            "feat: add thing",
+            |_| {
+                committed = true;
+                Ok(())
+            },
+        );
+
+        let error = result.unwrap_err().to_string();
+        assert!(!committed);
+        assert!(error.contains("rejected: feat: add thing"));
+    }
+
+    #[test]
+    fn test_passing_validator_commits() {
+        let mut committed_message = None;
+
+        commit_with_validation(
+            Some("test \"$CONVMIT_MESSAGE\" = \"fix: handle empty input\""),
+            "fix: handle empty input",
+            |message| {
+                committed_message = Some(message.to_string());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            committed_message.as_deref(),
+            Some("fix: handle empty input")
+        );
+    }
+
+    #[test]
+    fn test_context_command_output_reaches_prompt() {
+        let context =
+            run_context_command("printf 'Draft changelog:\\n- faster startup\\n'").unwrap();
+
+        let prompt =
+            crate::ai::build_user_prompt(&["src/main.rs".to_string()], "diff", Some(&context));
+
+        assert!(prompt.contains(
+            "<additional_context>\nDraft changelog:\n- faster startup\n    </additional_context>"
+        ));
+    }
+
+    #[test]
+    fn test_failing_context_command_is_an_error() {
+        let error = run_context_command("echo nope >&2; exit 2")
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("nope"));
+    }
+
+    #[test]
+    fn test_post_commit_command_sees_sha_and_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let captured = dir.path().join("env.txt");
+
+        run_post_commit_command(
+            &format!(
+                "printf '%s|%s' \"$CONVMIT_SHA\" \"$CONVMIT_MESSAGE\" > '{}'",
+                captured.display()
+            ),
+            "3f2a9c1",
+            "feat: add hook",
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&captured).unwrap(),
+            "3f2a9c1|feat: add hook"
+        );
+        assert!(run_post_commit_command("exit 3", "3f2a9c1", "feat: add hook").is_err());
+    }
+
+    #[test]
+    fn test_lint_command_receives_message_on_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let captured = dir.path().join("message.txt");
+
+        let outcome =
+            run_lint_command(&format!("cat > '{}'", captured.display()), "feat: add lint").unwrap();
+
+        assert_eq!(outcome, LintOutcome::Passed);
+        assert_eq!(
+            std::fs::read_to_string(&captured).unwrap(),
+            "feat: add lint"
+        );
+    }
+
+    #[test]
+    fn test_lint_command_failure_and_missing_binary() {
+        let outcome = run_lint_command("echo 'subject may not be empty'; exit 1", "oops").unwrap();
+        assert_eq!(
+            outcome,
+            LintOutcome::Failed("subject may not be empty".to_string())
+        );
+
+        let outcome = run_lint_command("convmit-missing-commitlint-binary", "feat: x").unwrap();
+        assert_eq!(outcome, LintOutcome::Unavailable);
+    }
+
+    #[test]
+    fn test_no_validator_commits_directly() {
+        let mut committed = false;
+
+        commit_with_validation(None, "docs: update readme", |_| {
+            committed = true;
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(committed);
+    }
+}
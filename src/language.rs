@@ -0,0 +1,121 @@
+use std::path::Path;
+
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    let path = Path::new(path);
+    let file_name = path.file_name()?.to_str()?;
+
+    match file_name {
+        "Dockerfile" => return Some("Dockerfile"),
+        "Makefile" | "makefile" => return Some("Makefile"),
+        "justfile" | "Justfile" => return Some("Just"),
+        _ => {}
+    }
+
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => "Rust",
+        "ts" | "tsx" | "mts" | "cts" => "TypeScript",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "py" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "swift" => "Swift",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "sh" | "bash" | "zsh" => "Shell",
+        "sql" => "SQL",
+        "html" | "htm" => "HTML",
+        "css" | "scss" | "sass" => "CSS",
+        "md" | "markdown" => "Markdown",
+        "yml" | "yaml" => "YAML",
+        "toml" => "TOML",
+        "json" => "JSON",
+        "xml" => "XML",
+        "nix" => "Nix",
+        "lua" => "Lua",
+        _ => return None,
+    };
+
+    Some(language)
+}
+
+pub fn detect_languages(files: &[String]) -> Vec<String> {
+    let mut languages: Vec<String> = Vec::new();
+
+    for language in files.iter().filter_map(|file| language_for_path(file)) {
+        if !languages.iter().any(|known| known == language) {
+            languages.push(language.to_string());
+        }
+    }
+
+    languages
+}
+
+pub fn tag_files(files: &[String]) -> Vec<String> {
+    files
+        .iter()
+        .map(|file| match language_for_path(file) {
+            Some(language) => format!("[{}] {}", language.to_lowercase(), file),
+            None => file.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_for_path() {
+        assert_eq!(language_for_path("src/ai.rs"), Some("Rust"));
+        assert_eq!(language_for_path("web/App.TSX"), Some("TypeScript"));
+        assert_eq!(language_for_path("README.md"), Some("Markdown"));
+        assert_eq!(language_for_path(".github/workflows/ci.yml"), Some("YAML"));
+        assert_eq!(language_for_path("docker/Dockerfile"), Some("Dockerfile"));
+        assert_eq!(language_for_path("LICENSE"), None);
+        assert_eq!(language_for_path("assets/logo.png"), None);
+    }
+
+    #[test]
+    fn test_detect_languages_deduplicates_in_order() {
+        let files = vec![
+            "src/main.rs".to_string(),
+            "README.md".to_string(),
+            "src/config.rs".to_string(),
+            "web/index.ts".to_string(),
+            ".github/workflows/ci.yaml".to_string(),
+            "docs/guide.md".to_string(),
+            "LICENSE".to_string(),
+        ];
+
+        assert_eq!(
+            detect_languages(&files),
+            vec!["Rust", "Markdown", "TypeScript", "YAML"]
+        );
+    }
+
+    #[test]
+    fn test_tag_files() {
+        let files = vec![
+            "src/main.rs".to_string(),
+            "Makefile".to_string(),
+            "LICENSE".to_string(),
+        ];
+
+        assert_eq!(
+            tag_files(&files),
+            vec!["[rust] src/main.rs", "[makefile] Makefile", "LICENSE"]
+        );
+    }
+
+    #[test]
+    fn test_detect_languages_without_known_extensions() {
+        let files = vec!["LICENSE".to_string(), "assets/logo.png".to_string()];
+
+        assert!(detect_languages(&files).is_empty());
+    }
+}
@@ -1,4 +1,19 @@
 pub mod ai;
 pub mod cli;
+pub mod commit;
 pub mod config;
+pub mod diff;
+pub mod generate;
 pub mod git;
+pub mod hooks;
+pub mod language;
+pub mod lint;
+pub mod model_cache;
+pub mod output;
+pub mod project;
+pub mod redact;
+pub mod report;
+pub mod roles;
+pub mod split;
+pub mod template;
+pub mod version;
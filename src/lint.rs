@@ -0,0 +1,387 @@
+use std::fmt::Display;
+
+use crate::commit::{self, ConventionalCommit};
+use crate::project::CommitOptions;
+
+pub const MIN_SUBJECT_LENGTH: usize = 50;
+pub const MAX_SUBJECT_LENGTH: usize = 72;
+
+// Used for the body when the project doesn't set its own wrap width
+const DEFAULT_BODY_WIDTH: usize = 72;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl LintCheck {
+    fn new(name: &'static str, passed: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintReport {
+    pub checks: Vec<LintCheck>,
+}
+
+impl LintReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|check| !check.passed).count()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubjectLength {
+    Short,
+    Ok,
+    Long,
+}
+
+impl SubjectLength {
+    pub fn classify(length: usize) -> Self {
+        if length < MIN_SUBJECT_LENGTH {
+            SubjectLength::Short
+        } else if length > MAX_SUBJECT_LENGTH {
+            SubjectLength::Long
+        } else {
+            SubjectLength::Ok
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthReport {
+    pub subject_length: usize,
+    pub subject: SubjectLength,
+    pub body_lines: usize,
+    pub longest_body_line: usize,
+}
+
+impl LengthReport {
+    pub fn from_commit(commit: &ConventionalCommit) -> Self {
+        let subject_length = commit.subject().chars().count();
+        let body_lines: Vec<&str> = commit
+            .body
+            .as_deref()
+            .map(|body| {
+                body.lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            subject_length,
+            subject: SubjectLength::classify(subject_length),
+            body_lines: body_lines.len(),
+            longest_body_line: body_lines
+                .iter()
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
+
+impl Display for LengthReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = match self.subject {
+            SubjectLength::Short => "short",
+            SubjectLength::Ok => "ok",
+            SubjectLength::Long => "long",
+        };
+        write!(
+            f,
+            "Subject: {} characters ({verdict}, aim for {MIN_SUBJECT_LENGTH}-{MAX_SUBJECT_LENGTH})",
+            self.subject_length
+        )?;
+        if self.body_lines > 0 {
+            write!(
+                f,
+                ", body: {} line(s), longest {}",
+                self.body_lines, self.longest_body_line
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Type and scope rules come from CommitOptions, so this agrees with --validate-file
+pub fn lint_message(message: &str, options: &CommitOptions) -> LintReport {
+    if commit::is_git_generated(message) {
+        return LintReport {
+            checks: vec![LintCheck::new("format", true, "written by git, left as is")],
+        };
+    }
+
+    let commit = match ConventionalCommit::parse(message) {
+        Ok(commit) => commit,
+        Err(err) => {
+            return LintReport {
+                checks: vec![LintCheck::new("format", false, err.to_string())],
+            };
+        }
+    };
+
+    LintReport {
+        checks: vec![
+            check_type(&commit, options),
+            check_scope(&commit, options),
+            check_subject_length(&commit),
+            check_body(message, options),
+            check_trailers(message, &commit),
+        ],
+    }
+}
+
+fn check_type(commit: &ConventionalCommit, options: &CommitOptions) -> LintCheck {
+    match options.type_violation(commit) {
+        Some(violation) => LintCheck::new("type", false, violation),
+        None => LintCheck::new("type", true, commit.commit_type.as_str()),
+    }
+}
+
+fn check_scope(commit: &ConventionalCommit, options: &CommitOptions) -> LintCheck {
+    match (options.scope_violation(commit), &commit.scope) {
+        (Some(violation), _) => LintCheck::new("scope", false, violation),
+        (None, Some(scope)) => LintCheck::new("scope", true, scope.as_str()),
+        (None, None) => LintCheck::new("scope", true, "none"),
+    }
+}
+
+// Measured like the verbose length report, so both agree on what is too long
+fn check_subject_length(commit: &ConventionalCommit) -> LintCheck {
+    let report = LengthReport::from_commit(commit);
+    LintCheck::new(
+        "subject length",
+        report.subject != SubjectLength::Long,
+        format!(
+            "{} characters, limit {MAX_SUBJECT_LENGTH}",
+            report.subject_length
+        ),
+    )
+}
+
+fn check_body(message: &str, options: &CommitOptions) -> LintCheck {
+    let mut lines = message.trim().lines().skip(1);
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        return LintCheck::new("body wrap", false, "no blank line after the subject");
+    }
+
+    let width = options.wrap.unwrap_or(DEFAULT_BODY_WIDTH);
+    let long_lines = lines.filter(|line| line.chars().count() > width).count();
+    if long_lines == 0 {
+        LintCheck::new("body wrap", true, format!("within {width} characters"))
+    } else {
+        LintCheck::new(
+            "body wrap",
+            false,
+            format!("{long_lines} line(s) longer than {width} characters"),
+        )
+    }
+}
+
+fn check_trailers(message: &str, commit: &ConventionalCommit) -> LintCheck {
+    // Lines like "Refs:#12" or a bare "Reviewed-by:" end up in the body instead of the footers
+    let after_subject = message
+        .trim()
+        .lines()
+        .skip(1)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let malformed = after_subject
+        .rsplit("\n\n")
+        .next()
+        .unwrap_or_default()
+        .lines()
+        .find(|line| looks_like_trailer(line) && !commit::is_footer_line(line));
+    if let Some(line) = malformed {
+        return LintCheck::new(
+            "trailers",
+            false,
+            format!(
+                "\"{}\" should be \"Token: value\" or \"Token #value\"",
+                line.trim()
+            ),
+        );
+    }
+
+    let empty: Vec<&str> = commit
+        .footers
+        .iter()
+        .filter(|(_, value)| value.trim().is_empty())
+        .map(|(token, _)| token.as_str())
+        .collect();
+
+    if !empty.is_empty() {
+        LintCheck::new(
+            "trailers",
+            false,
+            format!("empty value for {}", empty.join(", ")),
+        )
+    } else if commit.footers.is_empty() {
+        LintCheck::new("trailers", true, "none")
+    } else {
+        LintCheck::new(
+            "trailers",
+            true,
+            format!("{} well-formed", commit.footers.len()),
+        )
+    }
+}
+
+fn looks_like_trailer(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(token, _)| {
+        !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(report: &LintReport) -> Vec<&'static str> {
+        report
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_subject_length_classification() {
+        let report = |subject: &str| {
+            LengthReport::from_commit(&ConventionalCommit::parse(subject).unwrap()).subject
+        };
+
+        assert_eq!(report("fix: typo"), SubjectLength::Short);
+        assert_eq!(
+            report("feat(auth): add OAuth2 login support with refresh tokens"),
+            SubjectLength::Ok
+        );
+        assert_eq!(
+            report(
+                "feat(auth): add OAuth2 login support with refresh tokens and remember-me cookies"
+            ),
+            SubjectLength::Long
+        );
+        assert_eq!(
+            SubjectLength::classify(MIN_SUBJECT_LENGTH),
+            SubjectLength::Ok
+        );
+        assert_eq!(
+            SubjectLength::classify(MAX_SUBJECT_LENGTH),
+            SubjectLength::Ok
+        );
+        assert_eq!(
+            SubjectLength::classify(MAX_SUBJECT_LENGTH + 1),
+            SubjectLength::Long
+        );
+    }
+
+    #[test]
+    fn test_length_report_counts_body_lines() {
+        let commit =
+            ConventionalCommit::parse("fix: typo\n\nThe README misspelled the\nproject name.")
+                .unwrap();
+        let report = LengthReport::from_commit(&commit);
+
+        assert_eq!(report.body_lines, 2);
+        assert_eq!(report.longest_body_line, 25);
+        assert_eq!(
+            report.to_string(),
+            "Subject: 9 characters (short, aim for 50-72), body: 2 line(s), longest 25"
+        );
+    }
+
+    #[test]
+    fn test_clean_message_passes() {
+        let report = lint_message(
+            "feat(lint): add a standalone lint command\n\nRuns the validator without a model.\n\nRefs: #1000",
+            &CommitOptions::default(),
+        );
+
+        assert!(report.passed());
+        assert_eq!(
+            report
+                .checks
+                .iter()
+                .map(|check| check.name)
+                .collect::<Vec<_>>(),
+            vec!["type", "scope", "subject length", "body wrap", "trailers"]
+        );
+        assert_eq!(report.checks[4].detail, "1 well-formed");
+    }
+
+    #[test]
+    fn test_lint_agrees_with_validate_file() {
+        let options = CommitOptions::default();
+
+        for message in [
+            "merge: bring feature/oauth login support into main",
+            "Merge branch 'feature/oauth' into main",
+            "Revert \"feat(api): add pagination\"",
+            "fixup! feat(api): add pagination",
+        ] {
+            assert!(lint_message(message, &options).passed(), "{message}");
+            assert!(options.violations(message).is_empty(), "{message}");
+        }
+
+        let message = "feature(api): add pagination";
+        assert!(!lint_message(message, &options).passed());
+        assert_eq!(
+            options.violations(message),
+            vec![lint_message(message, &options).checks[0].detail.clone()]
+        );
+    }
+
+    #[test]
+    fn test_violations_are_reported() {
+        let options = CommitOptions {
+            require_scope: true,
+            forbidden_types: vec!["chore".to_string()],
+            wrap: Some(40),
+            ..Default::default()
+        };
+
+        let report = lint_message(
+            "chore: bump every dependency in the workspace to the latest compatible release\nNo blank line here.",
+            &options,
+        );
+        assert_eq!(
+            failed(&report),
+            vec!["type", "scope", "subject length", "body wrap"]
+        );
+        assert_eq!(report.failures(), 4);
+
+        let report = lint_message(
+            "feature(api): add pagination\n\nThis body line is far too long for the forty character wrap.",
+            &options,
+        );
+        assert_eq!(failed(&report), vec!["type", "body wrap"]);
+        assert!(report.checks[0].detail.contains("not one of"));
+
+        let report = lint_message("fix(api): handle empty input\n\nReviewed-by: ", &options);
+        assert_eq!(failed(&report), vec!["trailers"]);
+
+        let report = lint_message("fix(api): handle empty input\n\nRefs:#12", &options);
+        assert_eq!(failed(&report), vec!["trailers"]);
+        assert!(report.checks[4].detail.contains("\"Refs:#12\""));
+
+        let report = lint_message("just some words", &options);
+        assert!(!report.passed());
+        assert_eq!(failed(&report), vec!["format"]);
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::BTreeMap,
     env, fs,
-    io::Write,
+    io::{IsTerminal, Write},
     process::Command,
 };
 
@@ -9,38 +9,149 @@ use clap::Parser;
 use colored::*;
 use tempfile::NamedTempFile;
 
-use convmit::ai::{Model, create_client};
-use convmit::cli::Cli;
+use convmit::ai::{
+    self, BODY_INSTRUCTION, BODY_ONLY_INSTRUCTION, ClientOptions, EMPTY_COMMIT_INSTRUCTION,
+    EXPLAIN_TYPE_INSTRUCTION, GenerateCommitMessage, HttpOptions, LOCAL_MODEL_PREFIX, Model,
+    Prompt, PromptKind, UserPromptOptions, WHITESPACE_ONLY_INSTRUCTION, build_system_prompt_from,
+    build_user_prompt_with_options, create_client_with_options,
+};
+use convmit::cli::{Cli, Command as CliCommand, ConfigAction, OutputFormat, StyleSource};
+use convmit::commit;
 use convmit::config::Config;
-use convmit::git::Git;
+use convmit::diff;
+use convmit::generate::{self, Cancellable};
+use convmit::git::{CommitOverrides, DiffOptions, Git};
+use convmit::hooks::{self, LintOutcome};
+use convmit::lint;
+use convmit::model_cache::{self, ModelCache};
+use convmit::output;
+use convmit::project::{CommitOptions, ProjectConfig, load_pr_template};
+use convmit::redact;
+use convmit::report::Report;
+use convmit::roles;
+use convmit::split::{self, CommitGroup};
+use convmit::version;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    let (format, keep_going) = (cli.format, cli.keep_going);
+    let result = run(cli).await;
+
+    if format == OutputFormat::Json {
+        let report = Report::from_result(result.map_err(|err| err.to_string()));
+        println!("{}", report.to_json()?);
+        std::process::exit(report.exit_code(keep_going));
+    }
+
+    match result {
+        Err(err) if keep_going => {
+            output::error(&err.to_string());
+            Ok(())
+        }
+        Err(err) if output::in_github_actions() => {
+            output::error(&err.to_string());
+            std::process::exit(1);
+        }
+        result => result.map(|_| ()),
+    }
+}
+
+// Enough to show the habits without crowding out the diff
+const STYLE_EXAMPLE_COUNT: usize = 20;
+
+async fn run(cli: Cli) -> Result<Option<String>, Box<dyn std::error::Error>> {
     let mut config = Config::load()?;
+    if config.get_load_dotenv()
+        && let Ok(repo_root) = Git::repo_root()
+    {
+        config.load_dotenv(&repo_root)?;
+    }
+
+    if let Some(CliCommand::Version { verbose }) = &cli.command {
+        println!(
+            "{}",
+            version::version_report(*verbose, &Config::config_path()?)
+        );
+        return Ok(None);
+    }
+
+    if let Some(CliCommand::Scopes { limit }) = &cli.command {
+        let scopes = commit::scope_frequencies(&Git::recent_subjects(*limit)?);
+        if scopes.is_empty() {
+            println!("{}", "ℹ No scoped commits found".yellow());
+        }
+        for (scope, count) in scopes {
+            println!("{:>5}  {}", count.to_string().dimmed(), scope);
+        }
+        return Ok(None);
+    }
+
+    if let Some(CliCommand::Config {
+        action: ConfigAction::Reset { force },
+    }) = &cli.command
+    {
+        if !force && !confirm("Remove all stored API keys from the config?")? {
+            println!("{}", "ℹ Nothing was changed".yellow());
+            return Ok(None);
+        }
+
+        config.clear_keys()?;
+        println!("{}", "✓ Removed all stored API keys from config".green());
+        return Ok(None);
+    }
+
+    if let Some(CliCommand::Config {
+        action: ConfigAction::Sources,
+    }) = &cli.command
+    {
+        let mut sources = config.sources(!cli.model.is_empty());
+        if let Ok(repo_root) = Git::repo_root() {
+            let project = ProjectConfig::load(&repo_root)?;
+            sources.extend(
+                CommitOptions::sources(&project.commit, &cli.commit_overrides())
+                    .into_iter()
+                    .map(|(name, source)| (name, Some(source))),
+            );
+        }
+
+        for (name, source) in sources {
+            match source {
+                Some(source) => println!("  {name:<28} {source}"),
+                None => println!("  {name:<28} {}", "not set".dimmed()),
+            }
+        }
+        return Ok(None);
+    }
 
     if let Some(api_key) = cli.set_claude_key {
         config.set_claude_api_key(api_key)?;
         println!("{}", "✓ Claude API key saved to config".green());
-        return Ok(());
+        return Ok(None);
     }
 
     if let Some(api_key) = cli.set_openai_key {
         config.set_openai_api_key(api_key)?;
         println!("{}", "✓ OpenAI API key saved to config".green());
-        return Ok(());
+        return Ok(None);
     }
 
     if let Some(api_key) = cli.set_gemini_key {
         config.set_gemini_api_key(api_key)?;
         println!("{}", "✓ Gemini API key saved to config".green());
-        return Ok(());
+        return Ok(None);
     }
 
     if let Some(api_key) = cli.set_mistral_key {
         config.set_mistral_api_key(api_key)?;
         println!("{}", "✓ Mistral API key saved to config".green());
-        return Ok(());
+        return Ok(None);
+    }
+
+    if let Some(api_key) = cli.set_cohere_key {
+        config.set_cohere_api_key(api_key)?;
+        println!("{}", "✓ Cohere API key saved to config".green());
+        return Ok(None);
     }
 
     if let Some(model) = cli.set_default_model {
@@ -49,9 +160,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "{}",
             format!("✓ Default model set to {} in config", model).green()
         );
-        return Ok(());
+        return Ok(None);
     }
 
+    let http_options = HttpOptions {
+        version: config.get_http_version(),
+        offline: cli.no_network,
+        timeout_secs: cli.timeout.or(config.request_timeout_secs),
+    };
+
     if cli.list_models {
         println!("{}", "Available models:".blue().bold());
 
@@ -63,8 +180,35 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     acc
                 });
 
+        let cache_path = ModelCache::path()?;
+        let mut cache = ModelCache::load(&cache_path);
+
         for (provider, models) in models_by_provider {
             println!("\n{}", provider.cyan().bold());
+
+            let api_key = config.get_api_key_for_model(&models[0]);
+            if cli.remote && (api_key.is_some() || !config.requires_api_key(&models[0])) {
+                let base_url = config.get_base_url_for_model(&models[0]);
+                let remote = cache.get_or_fetch(
+                    provider,
+                    model_cache::now(),
+                    config.get_model_cache_ttl(),
+                    cli.refresh_models,
+                    || ai::list_remote_models(&models[0], api_key, base_url, http_options),
+                );
+                match remote.await {
+                    Ok(ids) => {
+                        for id in ids {
+                            println!("  {}", id.white());
+                        }
+                        continue;
+                    }
+                    Err(err) => output::warning(&format!(
+                        "Could not list remote {provider} models ({err}), showing built-in list"
+                    )),
+                }
+            }
+
             for model in models {
                 println!(
                     "  {} ({})",
@@ -73,79 +217,1144 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 );
             }
         }
-        return Ok(());
+
+        println!("\n{}", "Local".cyan().bold());
+        println!(
+            "  {} ({})",
+            format!("{LOCAL_MODEL_PREFIX}<name>").white(),
+            "llama.cpp server".dimmed()
+        );
+
+        if cli.remote {
+            cache.save(&cache_path)?;
+        }
+        return Ok(None);
+    }
+
+    if let Some(model) = &cli.model_info {
+        let info = model.info();
+        println!("{}", model.to_string().cyan().bold());
+        println!("  Provider:       {}", info.provider);
+        println!("  API id:         {}", info.api_id);
+        match info.context_window {
+            Some(window) => println!("  Context window: {window} tokens"),
+            None => println!("  Context window: {}", "unknown".dimmed()),
+        }
+        match info.pricing {
+            Some(pricing) => println!(
+                "  Pricing:        ${} input / ${} output per million tokens",
+                pricing.input_per_million, pricing.output_per_million
+            ),
+            None => println!("  Pricing:        {}", "unknown".dimmed()),
+        }
+        if let Some(replacement) = info.replacement {
+            println!(
+                "  Deprecated:     {}",
+                format!("yes, use {replacement} instead").yellow()
+            );
+        }
+        return Ok(None);
     }
 
-    let model = cli.model.unwrap_or(config.get_default_model());
+    let project = ProjectConfig::load(&Git::repo_root()?)?;
+    let commit_options = CommitOptions::resolve(&project.commit, &cli.commit_overrides());
+
+    if let Some(CliCommand::Lint { input }) = &cli.command {
+        let message = match fs::read_to_string(input) {
+            Ok(content) => commit::strip_comments(&content),
+            Err(_) => input.clone(),
+        };
+
+        let report = lint::lint_message(&message, &commit_options);
+        for check in &report.checks {
+            let mark = if check.passed {
+                "✓".green()
+            } else {
+                "✗".red()
+            };
+            println!("{mark} {:<15} {}", check.name, check.detail);
+        }
+        if !report.passed() {
+            return Err(anyhow::anyhow!(
+                "Commit message failed {} lint check(s)",
+                report.failures()
+            )
+            .into());
+        }
+        return Ok(None);
+    }
+
+    if let Some(path) = &cli.validate_file {
+        let message = commit::strip_comments(&fs::read_to_string(path)?);
+        let violations = commit_options.violations(&message);
+        if violations.is_empty() {
+            println!("{}", "✓ Commit message is valid".green());
+            return Ok(None);
+        }
+
+        for violation in &violations {
+            output::error(violation);
+        }
+        if !cli.fix {
+            return Err(anyhow::anyhow!(
+                "Commit message in {} is not a valid conventional commit",
+                path.display()
+            )
+            .into());
+        }
+        eprintln!(
+            "{}",
+            "ℹ Regenerating the message from the staged changes".yellow()
+        );
+    }
+
+    let model = cli
+        .model
+        .first()
+        .cloned()
+        .unwrap_or_else(|| config.resolve_default_model());
+    let fallback_models = cli.model.get(1..).unwrap_or_default();
+    if !fallback_models.is_empty() && (cli.count.is_some() || cli.two_phase || cli.split) {
+        output::warning(&format!(
+            "Only {model} is used with --count, --two-phase and --split, the rest of --model is ignored"
+        ));
+    }
+    if let Some(warning) = model.deprecation_warning() {
+        output::warning(&warning);
+    }
 
     // Validate model configuration
     config.validate_model_config(&model)?;
 
     // Get API key for the model
-    let api_key = config
-        .get_api_key_for_model(&model)
-        .ok_or(anyhow::anyhow!("No API key found for model {}", model))?;
+    let api_key = config.get_api_key_for_model(&model);
+    if config.requires_api_key(&model) && api_key.is_none() {
+        return Err(anyhow::anyhow!("No API key found for model {}", model).into());
+    }
 
-    let staged_files = Git::get_staged_files()?;
-    if staged_files.is_empty() {
-        println!("{}", "ℹ No files staged for commit".yellow());
-        return Ok(());
+    if cli.verbose
+        && let Some(source) = config.key_source(&model)
+    {
+        eprintln!(
+            "{}",
+            format!("ℹ Using {} API key from {}", model.provider(), source).dimmed()
+        );
     }
 
-    let filtered_files = apply_file_filters(staged_files, &cli.only, &cli.exclude);
-    if filtered_files.is_empty() {
-        println!(
+    if cli.model_name.is_some() && !model.is_openai() {
+        return Err(anyhow::anyhow!(
+            "--model-name is only supported for OpenAI models, not {}",
+            model
+        )
+        .into());
+    }
+
+    if cli.structured
+        && let Some(model) = std::iter::once(&model)
+            .chain(fallback_models)
+            .find(|model| !(model.is_openai() || model.is_local()))
+    {
+        return Err(anyhow::anyhow!(
+            "--structured is only supported for OpenAI-compatible models, not {}",
+            model
+        )
+        .into());
+    }
+
+    // A note documents HEAD itself, so diff it against its parent just like an amend
+    let diff_base = if cli.amend || cli.as_note {
+        Some(Git::amend_base()?)
+    } else if cli.since_tag {
+        match Git::latest_tag()? {
+            Some(tag) => {
+                if cli.verbose {
+                    eprintln!("{}", format!("ℹ Summarizing changes since {tag}").dimmed());
+                }
+                Some(tag)
+            }
+            None => {
+                eprintln!("{}", "ℹ No tags found to summarize from".yellow());
+                return Ok(None);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Amending or summarizing a range has nothing to do with a merge in progress
+    let merge_state = match diff_base {
+        Some(_) => None,
+        None => Git::merge_state()?,
+    };
+    if cli.verbose
+        && let Some(state) = &merge_state
+    {
+        eprintln!("{}", "ℹ Merge in progress, using the merge prompt".dimmed());
+        if state.branch.is_none() {
+            output::warning("Could not read the merged branch name from MERGE_MSG");
+        }
+    }
+
+    let staged_files = match &diff_base {
+        Some(base) => Git::get_staged_files_since(base)?,
+        None => Git::get_staged_files()?,
+    };
+    let empty_commit = staged_files.is_empty() && cli.allow_empty;
+    if staged_files.is_empty() && !empty_commit {
+        eprintln!("{}", "ℹ No files staged for commit".yellow());
+        return Ok(None);
+    }
+
+    let filtered_files = diff::apply_file_filters(staged_files, &cli.only, &cli.exclude);
+    if filtered_files.is_empty() && !empty_commit {
+        eprintln!(
             "{}",
             "ℹ No staged files matched the provided filters".yellow()
         );
-        return Ok(());
+        return Ok(None);
+    }
+
+    let diff_options = DiffOptions {
+        function_context: cli.function_context,
+        find_renames: cli.find_renames,
+        find_copies: cli.find_copies,
+    };
+    let raw_diff = if cli.labeled_diff {
+        let mut diffs = Vec::with_capacity(filtered_files.len());
+        for file in &filtered_files {
+            let diff = Git::get_diff(
+                diff_base.as_deref(),
+                std::slice::from_ref(file),
+                &diff_options,
+            )?;
+            diffs.push((file.clone(), diff));
+        }
+        diff::label_file_diffs(&diffs)
+    } else {
+        Git::get_diff(diff_base.as_deref(), &filtered_files, &diff_options)?
+    };
+    let raw_diff = if cli.patch {
+        select_hunks_interactively(&raw_diff)?
+    } else {
+        raw_diff
+    };
+    let diff = diff::annotate_submodules(&raw_diff);
+    let diff = match cli.head_lines {
+        Some(lines) => diff::head_lines_per_file(&diff, lines),
+        None => diff,
+    };
+    let diff = if cli.redact_secrets {
+        let (diff, redacted) = redact::redact_secrets(&diff);
+        if redacted > 0 {
+            output::warning(&format!(
+                "Redacted {redacted} possible secret(s) from the diff before sending"
+            ));
+        }
+        diff
+    } else {
+        diff
+    };
+    // --split sends each group on its own, which is what the limit asks for
+    if !cli.stat_only && !cli.split {
+        diff::check_diff_size(&diff, config.hard_max_diff_bytes)?;
     }
 
-    let diff = Git::get_staged_diff(&filtered_files)?;
+    let diff_stat = if cli.stat_context || cli.stat_only {
+        Some(Git::diff_stat(diff_base.as_deref(), &filtered_files)?)
+    } else {
+        None
+    };
+
+    let file_status = if diff_options.detects_renames() {
+        Some(Git::get_staged_status(
+            diff_base.as_deref(),
+            &filtered_files,
+            &diff_options,
+        )?)
+    } else {
+        None
+    };
 
-    let client = create_client(model, api_key);
-    let mut commit_message = client
-        .generate_commit_message(&filtered_files, &diff, cli.context.as_deref())
-        .await?;
+    let command_context = cli.context_command.as_deref().and_then(|command| {
+        match hooks::run_context_command(command) {
+            Ok(output) => Some(output).filter(|output| !output.trim().is_empty()),
+            Err(err) => {
+                output::warning(&format!("{err}, continuing without it"));
+                None
+            }
+        }
+    });
+    let mut context_parts = cli.context.clone();
+    context_parts.extend(command_context);
+    if let Some(state) = &merge_state {
+        context_parts.push(state.context());
+    }
+    if !filtered_files.is_empty() {
+        let numstat =
+            Git::get_staged_numstat_z(diff_base.as_deref(), &filtered_files, &diff_options)?;
+        if diff::has_binary_files(&numstat) {
+            let status = match &file_status {
+                Some(status) => status.clone(),
+                None => {
+                    Git::get_staged_status(diff_base.as_deref(), &filtered_files, &diff_options)?
+                }
+            };
+            if let Some(summary) = diff::summarize_binary_assets(&numstat, &status) {
+                context_parts.push(format!("Binary assets: {summary}"));
+            }
+        }
+    }
+    if empty_commit {
+        context_parts.push(ai::build_empty_commit_context(
+            Git::current_branch().ok().flatten().as_deref(),
+            Git::latest_tag().ok().flatten().as_deref(),
+            &Git::recent_subjects(10).unwrap_or_default(),
+        ));
+    }
+    let context = ai::join_context(&context_parts);
+
+    let mut prompt_options = UserPromptOptions {
+        context,
+        file_status,
+        diff_stat,
+        stat_only: cli.stat_only,
+        language_tags: cli.language_tags,
+        trim_files: cli.trim_prompt_files,
+    };
+
+    let body = if commit_options.body && commit_options.no_body_if_trivial {
+        let numstat = Git::get_staged_numstat(diff_base.as_deref(), &filtered_files)?;
+        commit_options.body_for(&diff::parse_numstat(&numstat))
+    } else {
+        commit_options.body
+    };
+    if cli.verbose && commit_options.body && !body {
+        eprintln!("{}", "ℹ Skipping the body for a trivial change".dimmed());
+    }
+
+    let project_instructions = commit_options.instructions();
+    let mut instructions: Vec<&str> = project_instructions.iter().map(String::as_str).collect();
+    if let Some(instruction) = cli.instruction.as_deref() {
+        instructions.push(instruction);
+    }
+    let pr_template_instruction = if cli.pr_template && body && !cli.keep_subject {
+        let template = load_pr_template(&Git::repo_root()?)?;
+        if template.is_none() {
+            output::warning("No pull request template found, generating a regular body");
+        }
+        template.map(|template| ai::pr_template_instruction(&template))
+    } else {
+        None
+    };
+    if cli.keep_subject {
+        instructions.push(BODY_ONLY_INSTRUCTION);
+    } else if let Some(instruction) = pr_template_instruction.as_deref() {
+        instructions.push(instruction);
+    } else if body {
+        instructions.push(BODY_INSTRUCTION);
+    }
+    let style_instruction = match cli.learn_style {
+        Some(source) => {
+            let author = match source {
+                StyleSource::Repo => None,
+                StyleSource::Mine => {
+                    let email = Git::user_email()?;
+                    if email.is_none() {
+                        output::warning(
+                            "No user.email in git config, learning style from all authors",
+                        );
+                    }
+                    email
+                }
+            };
+            let subjects =
+                Git::recent_subjects_by(STYLE_EXAMPLE_COUNT, author.as_deref()).unwrap_or_default();
+            (!subjects.is_empty()).then(|| ai::style_examples_instruction(&subjects))
+        }
+        None => None,
+    };
+    if let Some(instruction) = style_instruction.as_deref() {
+        instructions.push(instruction);
+    }
+    if cli.explain_type {
+        instructions.push(EXPLAIN_TYPE_INSTRUCTION);
+    }
+    if empty_commit {
+        instructions.push(EMPTY_COMMIT_INSTRUCTION);
+    } else if Git::is_whitespace_only(diff_base.as_deref(), &filtered_files)? {
+        if cli.verbose {
+            eprintln!("{}", "ℹ Only whitespace changed, suggesting style".dimmed());
+        }
+        instructions.push(WHITESPACE_ONLY_INSTRUCTION);
+    }
+    let inferred_type = if cli.infer_type && merge_state.is_none() {
+        roles::infer_type(&filtered_files)
+    } else {
+        None
+    };
+    let type_instruction = inferred_type
+        .as_ref()
+        .map(|commit_type| format!("use \"{commit_type}\" as the commit type"));
+    if let Some(instruction) = type_instruction.as_deref() {
+        instructions.push(instruction);
+    }
+    let mapped_scope = if merge_state.is_none() {
+        project.mapped_scope(&filtered_files)
+    } else {
+        None
+    };
+    let scope_instruction = mapped_scope
+        .as_ref()
+        .map(|scope| format!("use \"{scope}\" as the scope"));
+    if let Some(instruction) = scope_instruction.as_deref() {
+        instructions.push(instruction);
+    }
+
+    let client_options = ClientOptions {
+        base_url: config.get_base_url_for_model(&model),
+        max_tokens: cli.max_tokens,
+        body,
+        extra_fields: config.get_extra_request_fields(),
+        structured: cli.structured,
+        http: http_options,
+        model_name: cli.model_name.clone(),
+        log_request: cli.log_request,
+    };
+    let client = create_client_with_options(model.clone(), api_key, &client_options)?;
+
+    // Extra models get the same options, minus the endpoint and --model-name of the main one
+    let secondary_client = |secondary: &Model| -> anyhow::Result<Box<dyn GenerateCommitMessage>> {
+        config.validate_model_config(secondary)?;
+        let options = ClientOptions {
+            base_url: config.get_base_url_for_model(secondary),
+            model_name: None,
+            ..client_options.clone()
+        };
+        let api_key = config.get_api_key_for_model(secondary);
+        create_client_with_options(secondary.clone(), api_key, &options)
+    };
+    // A fallback without a key only fails the run if every model before it failed too
+    let fallback_clients: Vec<Box<dyn GenerateCommitMessage>> = fallback_models
+        .iter()
+        .map(|fallback| {
+            secondary_client(fallback)
+                .unwrap_or_else(|err| Box::new(generate::Unavailable::new(err)))
+        })
+        .collect();
+
+    // A body-only reply is never a full conventional commit, so don't escalate on it.
+    // A chain on the command line replaces the configured fallback.
+    let retry_model = cli
+        .retry_model
+        .clone()
+        .or_else(|| {
+            fallback_models
+                .is_empty()
+                .then(|| config.get_retry_model())
+                .flatten()
+        })
+        .filter(|retry_model| !cli.keep_subject && !cli.summary_only && *retry_model != model);
+    let retry_client = retry_model.as_ref().map(&secondary_client).transpose()?;
+    let system = match PromptKind::select(cli.summary_only, merge_state.is_some()) {
+        PromptKind::Summary => PromptKind::Summary.base_prompt().to_string(),
+        kind => build_system_prompt_from(
+            &ai::apply_description_style(kind.base_prompt(), config.get_description_style()),
+            project.prompt_preamble.as_deref(),
+            &instructions,
+        ),
+    };
+    let context_budget = generate::diff_byte_budget(
+        model.info().context_window,
+        &system,
+        client_options.resolved_max_tokens(),
+    );
+    let max_diff_tokens = cli.max_diff_tokens.or(config.max_diff_tokens);
+    let diff_budget = generate::cap_diff_budget(context_budget, max_diff_tokens);
+    let diff = match diff_budget {
+        Some(budget) => {
+            let estimated_tokens = generate::estimate_tokens(&diff);
+            let (diff, truncated) = diff::fit_diff(&diff, budget, config.get_diff_truncation());
+            if truncated {
+                match max_diff_tokens {
+                    Some(tokens) if diff_budget != context_budget => output::warning(&format!(
+                        "Diff of ~{estimated_tokens} tokens truncated to the {tokens} token budget"
+                    )),
+                    _ => output::warning(&format!(
+                        "Diff truncated to {budget} bytes to fit {model}'s context window"
+                    )),
+                }
+                // Files cut from the diff would otherwise vanish from the prompt entirely
+                prompt_options.trim_files = false;
+            }
+            diff
+        }
+        None => diff,
+    };
+
+    if cli.print_diff_sent && !cli.stat_only {
+        eprintln!("{}", diff::diff_sent_report(&diff).dimmed());
+    }
+
+    let prompt = Prompt {
+        system,
+        user: build_user_prompt_with_options(&filtered_files, &diff, &prompt_options),
+    };
+
+    if cli.split {
+        // Each group is committed from the working tree, which has to match what was staged
+        let unstaged = Git::unstaged_files(&filtered_files)?;
+        if !unstaged.is_empty() {
+            return Err(anyhow::anyhow!(
+                "--split commits whole files, but {} also changed after staging. Stage or stash those changes first",
+                unstaged.join(", ")
+            )
+            .into());
+        }
+        let groups = split::group_by_directory(&filtered_files);
+        let group_options = UserPromptOptions {
+            diff_stat: None,
+            file_status: None,
+            stat_only: false,
+            ..prompt_options.clone()
+        };
+        let mut prompts = Vec::with_capacity(groups.len());
+        for group in &groups {
+            let group_diff = Git::get_diff(diff_base.as_deref(), group, &diff_options)?;
+            let group_diff = match cli.head_lines {
+                Some(lines) => diff::head_lines_per_file(&group_diff, lines),
+                None => group_diff,
+            };
+            let group_diff = if cli.redact_secrets {
+                redact::redact_secrets(&group_diff).0
+            } else {
+                group_diff
+            };
+            let (group_diff, truncated) = match diff_budget {
+                Some(budget) => diff::fit_diff(&group_diff, budget, config.get_diff_truncation()),
+                None => (group_diff, false),
+            };
+            let options = UserPromptOptions {
+                trim_files: cli.trim_prompt_files && !truncated,
+                ..group_options.clone()
+            };
+            prompts.push(Prompt {
+                system: prompt.system.clone(),
+                user: build_user_prompt_with_options(group, &group_diff, &options),
+            });
+        }
+
+        let results = generate::generate_concurrently(
+            client.as_ref(),
+            &model,
+            retry_client.as_deref().zip(retry_model.as_ref()),
+            &prompts,
+            config.get_max_concurrency(),
+            config.get_short_response_retries(),
+        )
+        .await;
+
+        // Every message goes through the usual checks before anything is committed
+        let mut commit_groups = Vec::with_capacity(groups.len());
+        for (files, result) in groups.into_iter().zip(results) {
+            let generation = result?;
+            report_usage(&cli, &generation);
+            let staged = Staged {
+                files: &files,
+                inferred_type: if cli.infer_type && merge_state.is_none() {
+                    roles::infer_type(&files)
+                } else {
+                    None
+                },
+                mapped_scope: if merge_state.is_none() {
+                    project.mapped_scope(&files)
+                } else {
+                    None
+                },
+                empty: false,
+            };
+            let message =
+                prepare_message(&cli, &config, &commit_options, &staged, &model, &generation)?;
+            commit_groups.push(CommitGroup { files, message });
+        }
+
+        if !cli.execute {
+            let script = split::render_commit_script(&commit_groups, &split::message_delimiter())?;
+            if cli.format == OutputFormat::Text {
+                print!("{script}");
+            }
+            return Ok(Some(script));
+        }
+
+        commit_split_groups(&cli, &config, &commit_groups)?;
+        eprintln!(
+            "{}",
+            format!("✓ Created {} commits", commit_groups.len())
+                .green()
+                .bold()
+        );
+        let messages: Vec<&str> = commit_groups
+            .iter()
+            .map(|group| group.message.as_str())
+            .collect();
+        return Ok(Some(messages.join("\n\n")));
+    }
+    let generation = if let Some(count) = cli.count.filter(|count| *count > 1) {
+        match generate::run_cancellable(
+            generate::generate_candidates(
+                client.as_ref(),
+                &model,
+                &prompt,
+                count.into(),
+                config.get_max_concurrency(),
+                config.get_short_response_retries(),
+            ),
+            generate::ctrl_c(),
+        )
+        .await
+        {
+            Cancellable::Completed(Ok((mut candidates, usage))) => {
+                let index = pick_candidate(&candidates)?;
+                let mut generation = candidates.swap_remove(index);
+                generation.usage = usage;
+                Cancellable::Completed(Ok(generation))
+            }
+            Cancellable::Completed(Err(err)) => Cancellable::Completed(Err(err)),
+            Cancellable::Cancelled => Cancellable::Cancelled,
+        }
+    } else if cli.two_phase {
+        generate::run_cancellable(
+            generate::generate_two_phase(
+                client.as_ref(),
+                &model,
+                retry_client.as_deref().zip(retry_model.as_ref()),
+                &prompt,
+                config.get_short_response_retries(),
+            ),
+            generate::ctrl_c(),
+        )
+        .await
+    } else {
+        let print_token = |token: &str| {
+            eprint!("{token}");
+            let _ = std::io::stderr().flush();
+        };
+        let streaming = generate::Streaming::new(client.as_ref(), &print_token);
+        let streaming_retry = retry_client
+            .as_deref()
+            .map(|retry_client| generate::Streaming::new(retry_client, &print_token));
+        let streaming_fallbacks: Vec<_> = fallback_clients
+            .iter()
+            .map(|fallback| generate::Streaming::new(fallback.as_ref(), &print_token))
+            .collect();
+        let (client, retry_client): (&dyn GenerateCommitMessage, _) = if cli.stream {
+            (
+                &streaming,
+                streaming_retry
+                    .as_ref()
+                    .map(|retry_client| retry_client as &dyn GenerateCommitMessage),
+            )
+        } else {
+            (client.as_ref(), retry_client.as_deref())
+        };
+        let fallbacks: Vec<(&dyn GenerateCommitMessage, &Model)> = if cli.stream {
+            streaming_fallbacks
+                .iter()
+                .map(|fallback| fallback as &dyn GenerateCommitMessage)
+                .zip(fallback_models)
+                .collect()
+        } else {
+            fallback_clients
+                .iter()
+                .map(|fallback| fallback.as_ref())
+                .zip(fallback_models)
+                .collect()
+        };
+        let generation = generate::generate_cancellable(
+            client,
+            &model,
+            &fallbacks,
+            retry_client.zip(retry_model.as_ref()),
+            &prompt,
+            config.get_short_response_retries(),
+            generate::ctrl_c(),
+        )
+        .await;
+        if cli.stream {
+            eprintln!();
+        }
+        generation
+    };
+    let staged = Staged {
+        files: &filtered_files,
+        inferred_type,
+        mapped_scope,
+        empty: empty_commit,
+    };
+    let finished = generate::commit_if_completed(generation, |generation| {
+        let generation = generation?;
+        report_usage(&cli, &generation);
+        finish(&cli, &config, &commit_options, &staged, &model, generation)
+    });
+    match finished {
+        Cancellable::Completed(result) => result,
+        Cancellable::Cancelled => {
+            restore_terminal();
+            eprintln!();
+            output::error("Cancelled, nothing was committed");
+            std::process::exit(generate::CANCELLED_EXIT_CODE);
+        }
+    }
+}
+
+// What the generated message describes
+struct Staged<'a> {
+    files: &'a [String],
+    inferred_type: Option<String>,
+    mapped_scope: Option<String>,
+    empty: bool,
+}
+
+fn report_usage(cli: &Cli, generation: &generate::GenerationResult) {
+    match generation.usage {
+        Some(usage) if cli.format == OutputFormat::Text => {
+            let pricing = cli
+                .show_cost
+                .then(|| generation.model.info().pricing)
+                .flatten();
+            eprintln!("{}", usage.summary(pricing).dimmed());
+        }
+        None if cli.show_cost => output::warning(&format!(
+            "{} did not report token usage, no cost to show",
+            generation.model.provider()
+        )),
+        _ => {}
+    }
+}
+
+// Everything after a completed generation: post-processing, checks and the commit itself
+fn finish(
+    cli: &Cli,
+    config: &Config,
+    commit_options: &CommitOptions,
+    staged: &Staged,
+    model: &Model,
+    generation: generate::GenerationResult,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    if cli.summary_only {
+        let summary = generation.message.trim().to_string();
+        if cli.format == OutputFormat::Text {
+            println!("{summary}");
+        }
+        return Ok(Some(summary));
+    }
+
+    let commit_message = prepare_message(cli, config, commit_options, staged, model, &generation)?;
+
+    if cli.format == OutputFormat::Text {
+        println!("{}", commit_message);
+    }
+
+    if cli.commitlint {
+        match hooks::run_commitlint(&commit_message)? {
+            LintOutcome::Passed => eprintln!("{}", "✓ commitlint passed".green()),
+            LintOutcome::Failed(output) if cli.no_commit => {
+                output::error(&format!("commitlint failed\n{output}"));
+            }
+            LintOutcome::Failed(output) => {
+                return Err(anyhow::anyhow!("commitlint failed, not committing:\n{output}").into());
+            }
+            LintOutcome::Unavailable => {
+                output::warning("commitlint is not installed, skipping the check")
+            }
+        }
+    }
+
+    if let Some(path) = &cli.validate_file {
+        fs::write(path, format!("{commit_message}\n"))?;
+        eprintln!(
+            "{}",
+            format!("✓ Rewrote {} with generated message", path.display())
+                .green()
+                .bold()
+        );
+        return Ok(Some(commit_message));
+    }
+
+    if cli.as_note {
+        Git::add_note("HEAD", &commit_message)?;
+        eprintln!(
+            "{}",
+            "✓ Added generated message as a note on HEAD".green().bold()
+        );
+        return Ok(Some(commit_message));
+    }
+
+    if cli.should_commit() {
+        guard_detached_head(cli)?;
+
+        let validate_command = config.pre_commit_validate_command.as_deref();
+        let commit_overrides = CommitOverrides {
+            date: cli.date.clone(),
+            allow_empty: staged.empty,
+            method: config.get_commit_method(),
+            paths: Vec::new(),
+        };
+        let committed = if cli.amend {
+            hooks::commit_with_validation(validate_command, &commit_message, |message| {
+                Git::amend_with(message, &commit_overrides)
+            })?;
+            eprintln!("{}", "✓ Amended HEAD with generated message".green().bold());
+            true
+        } else {
+            let head_message = Git::head_message().ok();
+            // Without a HEAD there is nothing to compare against, so always commit
+            let index_differs = head_message.is_none() || Git::index_differs_from_head()?;
+            let committed = Git::commit_once(
+                head_message.as_deref(),
+                index_differs,
+                &commit_message,
+                |message| {
+                    hooks::commit_with_validation(validate_command, message, |message| {
+                        // Only leave the current branch once validation has passed
+                        if let Some(branch) = &cli.branch {
+                            switch_to_new_branch(branch, cli.reset_branch)?;
+                        }
+                        Git::commit_with(message, &commit_overrides)
+                    })
+                },
+            )?;
+
+            if committed {
+                eprintln!("{}", "✓ Committed with generated message".green().bold());
+            } else {
+                eprintln!(
+                    "{}",
+                    "ℹ HEAD already has this message and nothing new is staged, skipping commit"
+                        .yellow()
+                );
+            }
+            committed
+        };
+
+        if committed {
+            run_post_commit_command(cli, config, &commit_message)?;
+        }
+    }
+
+    Ok(Some(commit_message))
+}
+
+fn guard_detached_head(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    // Switching to the new branch attaches HEAD, so only check when staying put
+    let detached = cli.branch.is_none() && Git::is_detached_head()?;
+    Git::guard_detached_head(detached, cli.force)?;
+    if detached {
+        output::warning("Committing on a detached HEAD, create a branch to keep this commit");
+    }
+    Ok(())
+}
+
+fn run_post_commit_command(
+    cli: &Cli,
+    config: &Config,
+    commit_message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let post_commit_command = cli
+        .after_commit
+        .as_deref()
+        .or(config.post_commit_command.as_deref());
+    if let Some(command) = post_commit_command {
+        match hooks::run_post_commit_command(command, &Git::head_sha()?, commit_message) {
+            Err(err) if cli.strict => return Err(err.into()),
+            Err(err) => output::warning(&err.to_string()),
+            Ok(()) => {}
+        }
+    }
+    Ok(())
+}
+
+// Commits each group on its own, limited to the group's paths so the rest stay staged
+fn commit_split_groups(
+    cli: &Cli,
+    config: &Config,
+    groups: &[CommitGroup],
+) -> Result<(), Box<dyn std::error::Error>> {
+    guard_detached_head(cli)?;
+
+    let validate_command = config.pre_commit_validate_command.as_deref();
+    for group in groups {
+        let commit_overrides = CommitOverrides {
+            date: cli.date.clone(),
+            allow_empty: false,
+            method: config.get_commit_method(),
+            paths: group.files.clone(),
+        };
+        hooks::commit_with_validation(validate_command, &group.message, |message| {
+            Git::commit_with(message, &commit_overrides)
+        })?;
+        run_post_commit_command(cli, config, &group.message)?;
+    }
+    Ok(())
+}
+
+// Post-processing and checks shared by every generated commit message
+fn prepare_message(
+    cli: &Cli,
+    config: &Config,
+    commit_options: &CommitOptions,
+    staged: &Staged,
+    model: &Model,
+    generation: &generate::GenerationResult,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut commit_message = generation.message.clone();
+
+    if generate::is_placeholder(&commit_message) {
+        return Err(anyhow::anyhow!(
+            "The model echoed the format placeholder \"{}\" instead of writing a message",
+            commit_message.lines().next().unwrap_or_default().trim()
+        )
+        .into());
+    }
+
+    if cli.explain_type {
+        let (message, rationale) = ai::split_type_rationale(&commit_message);
+        commit_message = message;
+        match rationale {
+            Some(rationale) => eprintln!("{}", format!("ℹ Type rationale: {rationale}").dimmed()),
+            None => output::warning("The model did not explain its type choice"),
+        }
+    }
+
+    if let Some(commit_type) = staged.inferred_type.as_deref() {
+        commit_message = commit::force_type(&commit_message, commit_type);
+    }
+
+    if let Some(scope) = staged.mapped_scope.as_deref() {
+        commit_message = commit::force_scope(&commit_message, Some(scope));
+    }
+
+    if config.get_normalize_scope() {
+        commit_message = commit::normalize_message_scope(&commit_message)?;
+    }
+
+    if cli.interactive_scope {
+        commit_message = pick_scope(&commit_message)?;
+    }
+
+    if cli.keep_subject {
+        commit_message = commit::splice_body(&Git::head_message()?, &commit_message);
+    }
+
+    if let Some(width) = commit_options.wrap {
+        commit_message = commit::wrap_message_body(&commit_message, width);
+    }
+
+    if cli.attribution || config.get_attribution() {
+        let attributed_model = generation.api_id(model, cli.model_name.as_deref());
+        commit_message = commit::append_trailer(
+            &commit_message,
+            commit::ATTRIBUTION_TOKEN,
+            &commit::attribution_value(&attributed_model),
+        );
+    }
 
     if cli.edit {
-        commit_message = edit_commit_message(&commit_message)?;
+        let scissors_context = config.get_edit_scissors().then(|| {
+            std::iter::once("Staged files:".to_string())
+                .chain(staged.files.iter().map(|file| format!("  {file}")))
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+        commit_message = edit_commit_message(&commit_message, scissors_context.as_deref())?;
+    }
+
+    if cli.verbose
+        && let Ok(commit) = commit::ConventionalCommit::parse(&commit_message)
+    {
+        eprintln!(
+            "{}",
+            format!("ℹ {}", lint::LengthReport::from_commit(&commit)).dimmed()
+        );
+    }
+
+    commit_options.check(&commit_message)?;
+
+    // Rendered subjects need not parse as conventional commits, so the template goes
+    // last, after everything that re-parses the message
+    if let Some(template) = &commit_options.subject_template
+        && !cli.keep_subject
+    {
+        commit_message = template.apply(&commit_message)?;
     }
 
-    println!("{}", commit_message);
+    Ok(commit_message)
+}
 
-    if !cli.no_commit {
-        Git::commit(&commit_message)?;
-        println!("{}", "✓ Committed with generated message".green().bold());
+fn switch_to_new_branch(branch: &str, reset: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if reset && Git::branch_exists(branch)? {
+        output::warning(&format!(
+            "Resetting existing branch {branch} to HEAD, commits only on it will be lost"
+        ));
     }
 
+    Git::create_branch(branch, reset)?;
+    eprintln!("{}", format!("✓ Switched to new branch {branch}").green());
     Ok(())
 }
 
-fn apply_file_filters(
-    staged_files: Vec<String>,
-    only: &[String],
-    exclude: &[String],
-) -> Vec<String> {
-    let only_set: HashSet<&str> = only.iter().map(|s| s.as_str()).collect();
-    let exclude_set: HashSet<&str> = exclude.iter().map(|s| s.as_str()).collect();
+fn confirm(question: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    eprint!("{} ", format!("{question} [y/N]").blue());
+    std::io::stderr().flush()?;
 
-    staged_files
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn pick_candidate(
+    candidates: &[generate::GenerationResult],
+) -> Result<usize, Box<dyn std::error::Error>> {
+    if candidates.len() == 1 {
+        return Ok(0);
+    }
+
+    for (index, candidate) in candidates.iter().enumerate() {
+        let mut lines = candidate.message.lines();
+        eprintln!(
+            "\n  {} {}",
+            format!("[{}]", index + 1).yellow(),
+            lines.next().unwrap_or_default().bold()
+        );
+        for line in lines {
+            eprintln!("      {}", line.dimmed());
+        }
+    }
+    eprint!("\n{}", "Pick a message (Enter keeps 1): ".blue());
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(0);
+    }
+
+    let index = input
+        .parse::<usize>()
+        .map_err(|_| anyhow::anyhow!("Expected a number between 1 and {}", candidates.len()))?;
+    Ok(index
+        .checked_sub(1)
+        .filter(|index| *index < candidates.len())
+        .ok_or_else(|| anyhow::anyhow!("No message numbered {index}"))?)
+}
+
+const SCOPE_PICKER_LIMIT: usize = 15;
+
+fn pick_scope(message: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let current = commit::ConventionalCommit::parse(message)
+        .ok()
+        .and_then(|commit| commit.scope);
+    let scopes: Vec<String> = commit::scope_frequencies(&Git::recent_subjects(500)?)
         .into_iter()
-        .filter(|file| {
-            (only_set.is_empty() || only_set.contains(file.as_str()))
-                && !exclude_set.contains(file.as_str())
-        })
-        .collect()
+        .take(SCOPE_PICKER_LIMIT)
+        .map(|(scope, _)| scope)
+        .collect();
+
+    eprintln!("\n{}", "Scopes used in recent commits:".blue().bold());
+    eprintln!("  {} {}", "[0]".yellow(), "no scope".dimmed());
+    for (index, scope) in scopes.iter().enumerate() {
+        eprintln!("  {} {}", format!("[{}]", index + 1).yellow(), scope);
+    }
+    eprint!(
+        "\n{}",
+        format!(
+            "Pick a scope, or type a new one (Enter keeps {}): ",
+            current.as_deref().unwrap_or("no scope")
+        )
+        .blue()
+    );
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+
+    let scope = match input.parse::<usize>() {
+        _ if input.is_empty() => return Ok(message.to_string()),
+        Ok(0) => None,
+        Ok(index) => Some(
+            scopes
+                .get(index - 1)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No scope numbered {index}"))?,
+        ),
+        Err(_) => Some(commit::normalize_scope(input)?),
+    };
+
+    Ok(commit::force_scope(message, scope.as_deref()))
+}
+
+fn restore_terminal() {
+    if std::io::stderr().is_terminal() {
+        // Reset colors and make sure the cursor is visible again
+        eprint!("\x1b[0m\x1b[?25h");
+    }
+}
+
+fn select_hunks_interactively(raw_diff: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let files = diff::parse_file_diffs(raw_diff);
+    let hunk_count: usize = files.iter().map(|file| file.hunks.len()).sum();
+    if hunk_count == 0 {
+        return Ok(raw_diff.to_string());
+    }
+
+    let mut index = 0;
+    for file in &files {
+        eprintln!("\n{}", file.path.cyan().bold());
+        for hunk in &file.hunks {
+            index += 1;
+            eprintln!(
+                "  {} {}",
+                format!("[{index}]").yellow(),
+                hunk.header.dimmed()
+            );
+            for line in hunk.lines.iter().take(3) {
+                eprintln!("      {line}");
+            }
+        }
+    }
+
+    eprint!(
+        "\n{}",
+        "Hunks to leave out of the message (e.g. 1,3-4, Enter to keep all): ".blue()
+    );
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+
+    let excluded = diff::parse_hunk_selection(&input, hunk_count)?;
+    Ok(diff::render_file_diffs(&diff::select_hunks(
+        &files, &excluded,
+    )))
 }
 
-fn edit_commit_message(initial_message: &str) -> Result<String, Box<dyn std::error::Error>> {
-    edit_commit_message_inner(initial_message).map_err(|err| err.into())
+fn edit_commit_message(
+    initial_message: &str,
+    scissors_context: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    edit_commit_message_inner(initial_message, scissors_context).map_err(|err| err.into())
 }
 
-fn edit_commit_message_inner(initial_message: &str) -> anyhow::Result<String> {
+fn edit_commit_message_inner(
+    initial_message: &str,
+    scissors_context: Option<&str>,
+) -> anyhow::Result<String> {
     let mut file = NamedTempFile::new()?;
-    write!(file, "{}", initial_message)?;
+    match scissors_context {
+        Some(context) => write!(file, "{}", commit::add_scissors(initial_message, context))?,
+        None => write!(file, "{}", initial_message)?,
+    }
     file.flush()?;
 
     let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
@@ -167,7 +1376,7 @@ fn edit_commit_message_inner(initial_message: &str) -> anyhow::Result<String> {
     }
 
     let edited = fs::read_to_string(file.path())?;
-    Ok(edited.trim_end().to_string())
+    Ok(commit::strip_scissors(&edited))
 }
 
 fn parse_editor_command(editor: &str) -> anyhow::Result<Vec<String>> {
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+const CACHE_FILE: &str = "models.json";
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelCache {
+    providers: BTreeMap<String, CachedModels>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedModels {
+    fetched_at: u64,
+    models: Vec<String>,
+}
+
+impl ModelCache {
+    pub fn path() -> Result<PathBuf> {
+        let config_path = Config::config_path()?;
+        let config_dir = config_path
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+        Ok(config_dir.join(CACHE_FILE))
+    }
+
+    // A missing or unreadable cache is treated as empty, it only ever costs a refetch
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn fresh(&self, provider: &str, now: u64, ttl: Duration) -> Option<&[String]> {
+        self.providers
+            .get(provider)
+            .filter(|cached| now.saturating_sub(cached.fetched_at) < ttl.as_secs())
+            .map(|cached| cached.models.as_slice())
+    }
+
+    pub async fn get_or_fetch<F, Fut>(
+        &mut self,
+        provider: &str,
+        now: u64,
+        ttl: Duration,
+        refresh: bool,
+        fetch: F,
+    ) -> Result<Vec<String>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<String>>>,
+    {
+        if !refresh && let Some(models) = self.fresh(provider, now, ttl) {
+            return Ok(models.to_vec());
+        }
+
+        let models = fetch().await?;
+        self.providers.insert(
+            provider.to_string(),
+            CachedModels {
+                fetched_at: now,
+                models: models.clone(),
+            },
+        );
+        Ok(models)
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    async fn fetch_counted(
+        cache: &mut ModelCache,
+        now: u64,
+        refresh: bool,
+        calls: &AtomicUsize,
+    ) -> Vec<String> {
+        cache
+            .get_or_fetch("OpenAI", now, DEFAULT_TTL, refresh, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(vec![format!("gpt-fetched-{now}")])
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fresh_cache_is_used() {
+        let calls = AtomicUsize::new(0);
+        let mut cache = ModelCache::default();
+
+        let first = fetch_counted(&mut cache, NOW, false, &calls).await;
+        let second = fetch_counted(&mut cache, NOW + 60 * 60, false, &calls).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_stale_cache_or_refresh_triggers_refetch() {
+        let calls = AtomicUsize::new(0);
+        let mut cache = ModelCache::default();
+
+        fetch_counted(&mut cache, NOW, false, &calls).await;
+        let stale = NOW + DEFAULT_TTL.as_secs();
+        assert_eq!(
+            fetch_counted(&mut cache, stale, false, &calls).await,
+            vec![format!("gpt-fetched-{stale}")]
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        fetch_counted(&mut cache, stale, true, &calls).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cache_round_trips_through_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CACHE_FILE);
+        assert_eq!(ModelCache::load(&path), ModelCache::default());
+
+        let calls = AtomicUsize::new(0);
+        let mut cache = ModelCache::default();
+        fetch_counted(&mut cache, NOW, false, &calls).await;
+        cache.save(&path).unwrap();
+
+        let loaded = ModelCache::load(&path);
+        assert_eq!(
+            loaded.fresh("OpenAI", NOW, DEFAULT_TTL),
+            Some(&[format!("gpt-fetched-{NOW}")][..])
+        );
+        assert_eq!(loaded.fresh("Claude", NOW, DEFAULT_TTL), None);
+    }
+}
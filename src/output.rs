@@ -0,0 +1,83 @@
+use colored::*;
+
+const GITHUB_ACTIONS_VAR: &str = "GITHUB_ACTIONS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Warning,
+    Error,
+}
+
+pub fn in_github_actions() -> bool {
+    std::env::var(GITHUB_ACTIONS_VAR).is_ok_and(|value| value == "true")
+}
+
+pub fn warning(message: &str) {
+    eprintln!("{}", format_warning(message));
+}
+
+pub fn error(message: &str) {
+    eprintln!("{}", format_error(message));
+}
+
+pub fn format_warning(message: &str) -> String {
+    format_message(Level::Warning, message, in_github_actions())
+}
+
+pub fn format_error(message: &str) -> String {
+    format_message(Level::Error, message, in_github_actions())
+}
+
+// Workflow commands surface as annotations on the run and the pull request
+fn format_message(level: Level, message: &str, github_actions: bool) -> String {
+    match (level, github_actions) {
+        (Level::Warning, true) => format!("::warning::{}", escape_workflow_data(message)),
+        (Level::Error, true) => format!("::error::{}", escape_workflow_data(message)),
+        (Level::Warning, false) => format!("⚠ {message}").yellow().to_string(),
+        (Level::Error, false) => format!("✗ {message}").red().bold().to_string(),
+    }
+}
+
+// A raw newline would end the workflow command early
+fn escape_workflow_data(message: &str) -> String {
+    message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_format_follows_github_actions_env() {
+        unsafe {
+            std::env::set_var(GITHUB_ACTIONS_VAR, "true");
+        }
+        assert_eq!(
+            format_warning("Diff truncated"),
+            "::warning::Diff truncated"
+        );
+        assert_eq!(
+            format_error("commitlint failed\n1 problem, 100% bad"),
+            "::error::commitlint failed%0A1 problem, 100%25 bad"
+        );
+
+        unsafe {
+            std::env::remove_var(GITHUB_ACTIONS_VAR);
+        }
+        let plain = format_warning("Diff truncated");
+        assert!(!plain.starts_with("::"));
+        assert!(plain.contains("⚠ Diff truncated"));
+    }
+
+    #[test]
+    fn test_format_message_levels() {
+        assert_eq!(
+            format_message(Level::Error, "Cancelled", true),
+            "::error::Cancelled"
+        );
+        assert!(format_message(Level::Error, "Cancelled", false).contains("✗ Cancelled"));
+    }
+}
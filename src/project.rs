@@ -0,0 +1,547 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::commit::{self, ConventionalCommit};
+use crate::config::Source;
+use crate::diff::Churn;
+use crate::template::SubjectTemplate;
+
+pub const PROJECT_CONFIG_FILE: &str = ".convmit.toml";
+pub const PROMPT_PREAMBLE_FILE: &str = ".convmit/prompt.md";
+
+// The locations GitHub itself checks for a single pull request template
+pub const PR_TEMPLATE_FILES: &[&str] = &[
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    ".github/pull_request_template.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    "pull_request_template.md",
+    "docs/PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    pub commit: CommitDefaults,
+    pub scope_map: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub prompt_preamble: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CommitDefaults {
+    pub body: Option<bool>,
+    pub wrap: Option<usize>,
+    pub require_scope: Option<bool>,
+    pub forbidden_types: Option<Vec<String>>,
+    pub no_body_if_trivial: Option<bool>,
+    pub subject_template: Option<SubjectTemplate>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommitOptions {
+    pub body: bool,
+    pub wrap: Option<usize>,
+    pub require_scope: bool,
+    pub forbidden_types: Vec<String>,
+    pub no_body_if_trivial: bool,
+    pub subject_template: Option<SubjectTemplate>,
+}
+
+impl ProjectConfig {
+    pub fn load(repo_root: &Path) -> Result<Self> {
+        let path = repo_root.join(PROJECT_CONFIG_FILE);
+        let mut project: Self = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            toml::from_str(&content).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to parse project config at {}: {}",
+                    path.display(),
+                    e.message()
+                )
+            })?
+        } else {
+            Self::default()
+        };
+
+        project.prompt_preamble = load_prompt_preamble(repo_root)?;
+        Ok(project)
+    }
+
+    // Only returns a scope when every staged file resolves to the same one
+    pub fn mapped_scope(&self, files: &[String]) -> Option<String> {
+        let mut scopes = files.iter().map(|file| self.scope_for_path(file));
+        let first = scopes.next()??;
+        scopes
+            .all(|scope| scope == Some(first))
+            .then(|| first.to_string())
+    }
+
+    fn scope_for_path(&self, path: &str) -> Option<&str> {
+        self.scope_map
+            .iter()
+            .filter(|(prefix, _)| {
+                let prefix = prefix.trim_end_matches('/');
+                path.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|(prefix, _)| prefix.trim_end_matches('/').len())
+            .map(|(_, scope)| scope.as_str())
+    }
+}
+
+fn load_prompt_preamble(repo_root: &Path) -> Result<Option<String>> {
+    let path = repo_root.join(PROMPT_PREAMBLE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let content = content.trim();
+    Ok((!content.is_empty()).then(|| content.to_string()))
+}
+
+pub fn load_pr_template(repo_root: &Path) -> Result<Option<String>> {
+    let Some(path) = PR_TEMPLATE_FILES
+        .iter()
+        .map(|file| repo_root.join(file))
+        .find(|path| path.is_file())
+    else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(&path)?;
+    let content = content.trim();
+    Ok((!content.is_empty()).then(|| content.to_string()))
+}
+
+impl CommitOptions {
+    pub fn resolve(defaults: &CommitDefaults, overrides: &CommitDefaults) -> Self {
+        Self {
+            body: overrides.body.or(defaults.body).unwrap_or(false),
+            wrap: overrides.wrap.or(defaults.wrap).filter(|wrap| *wrap > 0),
+            require_scope: overrides
+                .require_scope
+                .or(defaults.require_scope)
+                .unwrap_or(false),
+            forbidden_types: overrides
+                .forbidden_types
+                .clone()
+                .or_else(|| defaults.forbidden_types.clone())
+                .unwrap_or_default(),
+            no_body_if_trivial: overrides
+                .no_body_if_trivial
+                .or(defaults.no_body_if_trivial)
+                .unwrap_or(false),
+            subject_template: overrides
+                .subject_template
+                .clone()
+                .or_else(|| defaults.subject_template.clone()),
+        }
+    }
+
+    // Mirrors `resolve`, recording which side supplied each value
+    pub fn sources(
+        defaults: &CommitDefaults,
+        overrides: &CommitDefaults,
+    ) -> Vec<(&'static str, Source)> {
+        fn source<T>(override_value: &Option<T>, default_value: &Option<T>) -> Source {
+            if override_value.is_some() {
+                Source::Cli
+            } else if default_value.is_some() {
+                Source::Project
+            } else {
+                Source::Default
+            }
+        }
+
+        vec![
+            ("commit.body", source(&overrides.body, &defaults.body)),
+            ("commit.wrap", source(&overrides.wrap, &defaults.wrap)),
+            (
+                "commit.require_scope",
+                source(&overrides.require_scope, &defaults.require_scope),
+            ),
+            (
+                "commit.forbidden_types",
+                source(&overrides.forbidden_types, &defaults.forbidden_types),
+            ),
+            (
+                "commit.no_body_if_trivial",
+                source(&overrides.no_body_if_trivial, &defaults.no_body_if_trivial),
+            ),
+            (
+                "commit.subject_template",
+                source(&overrides.subject_template, &defaults.subject_template),
+            ),
+        ]
+    }
+
+    pub fn body_for(&self, churn: &Churn) -> bool {
+        self.body && !(self.no_body_if_trivial && churn.is_trivial())
+    }
+
+    pub fn instructions(&self) -> Vec<String> {
+        let mut instructions = Vec::new();
+        if self.require_scope {
+            instructions.push("Always include a scope in the subject line.".to_string());
+        }
+        if !self.forbidden_types.is_empty() {
+            instructions.push(format!(
+                "Never use these commit types: {}.",
+                self.forbidden_types.join(", ")
+            ));
+        }
+        instructions
+    }
+
+    pub fn check(&self, message: &str) -> Result<()> {
+        if !self.require_scope && self.forbidden_types.is_empty() {
+            return Ok(());
+        }
+
+        match self.violations(message).into_iter().next() {
+            Some(violation) => Err(anyhow::anyhow!(violation)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn violations(&self, message: &str) -> Vec<String> {
+        // Merges, reverts and fixups keep git's own wording until they are squashed away
+        if commit::is_git_generated(message) {
+            return Vec::new();
+        }
+
+        let commit = match ConventionalCommit::parse(message) {
+            Ok(commit) => commit,
+            Err(err) => return vec![err.to_string()],
+        };
+
+        self.scope_violation(&commit)
+            .into_iter()
+            .chain(self.type_violation(&commit))
+            .collect()
+    }
+
+    pub fn scope_violation(&self, commit: &ConventionalCommit) -> Option<String> {
+        (self.require_scope && commit.scope.is_none()).then(|| {
+            format!(
+                "Commit message \"{}\" has no scope, but this project requires one",
+                commit.subject()
+            )
+        })
+    }
+
+    pub fn type_violation(&self, commit: &ConventionalCommit) -> Option<String> {
+        let commit_type = commit.commit_type.as_str();
+        if self
+            .forbidden_types
+            .iter()
+            .any(|forbidden| forbidden == commit_type)
+        {
+            Some(format!(
+                "Commit type \"{commit_type}\" is not allowed in this project"
+            ))
+        } else if !commit::KNOWN_TYPES.contains(&commit_type) {
+            Some(format!(
+                "Commit type \"{commit_type}\" is not one of {}",
+                commit::KNOWN_TYPES.join(", ")
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> ProjectConfig {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_commit_section_sets_defaults() {
+        let project = parse(
+            r#"
+[commit]
+body = true
+wrap = 72
+require_scope = true
+forbidden_types = ["chore"]
+"#,
+        );
+
+        let options = CommitOptions::resolve(&project.commit, &CommitDefaults::default());
+
+        assert_eq!(
+            options,
+            CommitOptions {
+                body: true,
+                wrap: Some(72),
+                require_scope: true,
+                forbidden_types: vec!["chore".to_string()],
+                no_body_if_trivial: false,
+                subject_template: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_cli_overrides_take_precedence() {
+        let project = parse(
+            r#"
+[commit]
+body = true
+wrap = 72
+"#,
+        );
+        let overrides = CommitDefaults {
+            body: Some(false),
+            wrap: Some(100),
+            ..Default::default()
+        };
+
+        let options = CommitOptions::resolve(&project.commit, &overrides);
+
+        assert!(!options.body);
+        assert_eq!(options.wrap, Some(100));
+        assert!(!options.require_scope);
+        assert!(options.forbidden_types.is_empty());
+
+        let sources = CommitOptions::sources(
+            &project.commit,
+            &CommitDefaults {
+                body: Some(false),
+                ..Default::default()
+            },
+        );
+        assert_eq!(sources[0], ("commit.body", Source::Cli));
+        assert_eq!(sources[1], ("commit.wrap", Source::Project));
+        assert_eq!(sources[2], ("commit.require_scope", Source::Default));
+    }
+
+    #[test]
+    fn test_missing_section_uses_builtin_defaults() {
+        let options = CommitOptions::resolve(&parse("").commit, &CommitDefaults::default());
+
+        assert_eq!(options, CommitOptions::default());
+    }
+
+    #[test]
+    fn test_body_suppressed_for_trivial_churn() {
+        let options = CommitOptions {
+            body: true,
+            no_body_if_trivial: true,
+            ..Default::default()
+        };
+        let trivial = Churn { files: 1, lines: 2 };
+        let larger = Churn {
+            files: 3,
+            lines: 40,
+        };
+
+        assert!(!options.body_for(&trivial));
+        assert!(options.body_for(&larger));
+
+        let forced = CommitOptions::resolve(
+            &CommitDefaults {
+                body: Some(true),
+                no_body_if_trivial: Some(true),
+                ..Default::default()
+            },
+            &CommitDefaults {
+                no_body_if_trivial: Some(false),
+                ..Default::default()
+            },
+        );
+        assert!(forced.body_for(&trivial));
+        assert!(!CommitOptions::default().body_for(&larger));
+    }
+
+    #[test]
+    fn test_check_enforces_scope_and_forbidden_types() {
+        let options = CommitOptions {
+            require_scope: true,
+            forbidden_types: vec!["chore".to_string()],
+            ..Default::default()
+        };
+
+        assert!(options.check("feat(cli): add flag").is_ok());
+        assert!(
+            options
+                .check("feat: add flag")
+                .unwrap_err()
+                .to_string()
+                .contains("no scope")
+        );
+        assert!(
+            options
+                .check("chore(deps): bump reqwest")
+                .unwrap_err()
+                .to_string()
+                .contains("\"chore\" is not allowed")
+        );
+        assert!(CommitOptions::default().check("Update things").is_ok());
+    }
+
+    #[test]
+    fn test_violations_for_sample_messages() {
+        let options = CommitOptions {
+            require_scope: true,
+            forbidden_types: vec!["wip".to_string()],
+            ..Default::default()
+        };
+
+        assert!(
+            options
+                .violations("fix(parser): handle empty input")
+                .is_empty()
+        );
+        assert_eq!(
+            options.violations("Fixed the parser"),
+            vec!["Missing ':' after the commit type in \"Fixed the parser\"".to_string()]
+        );
+        assert_eq!(options.violations("wip: stuff").len(), 2);
+        assert!(
+            CommitOptions::default()
+                .violations("docs: update readme")
+                .is_empty()
+        );
+        assert_eq!(CommitOptions::default().violations("").len(), 1);
+    }
+
+    #[test]
+    fn test_git_generated_messages_pass_validation() {
+        let options = CommitOptions {
+            require_scope: true,
+            forbidden_types: vec!["wip".to_string()],
+            ..Default::default()
+        };
+
+        for message in [
+            "Merge branch 'feature/oauth' into main",
+            "Merge pull request #12 from omfj/pagination",
+            "Revert \"feat(api): add pagination\"\n\nThis reverts commit 3f2a9c1.",
+            "fixup! feat(api): add pagination",
+            "squash! feat(api): add pagination",
+            "amend! feat(api): add pagination",
+        ] {
+            assert!(options.violations(message).is_empty(), "{message}");
+        }
+
+        assert_eq!(options.violations("Merge the two parsers").len(), 1);
+        assert_eq!(options.violations("fixup: typo").len(), 2);
+    }
+
+    #[test]
+    fn test_scope_map_longest_prefix() {
+        let project = parse(
+            r#"
+[scope_map]
+"packages/api/" = "api"
+"packages/api/auth" = "auth"
+"packages/web" = "web"
+"#,
+        );
+        let scope = |files: &[&str]| {
+            let files: Vec<String> = files.iter().map(|file| file.to_string()).collect();
+            project.mapped_scope(&files)
+        };
+
+        assert_eq!(
+            scope(&["packages/api/src/lib.rs", "packages/api/Cargo.toml"]),
+            Some("api".to_string())
+        );
+        assert_eq!(
+            scope(&["packages/api/auth/token.rs"]),
+            Some("auth".to_string())
+        );
+        assert_eq!(
+            scope(&["packages/api/src/lib.rs", "packages/api/auth/token.rs"]),
+            None
+        );
+        assert_eq!(scope(&["packages/web/index.ts", "README.md"]), None);
+        assert_eq!(scope(&["packages/api-gateway/main.go"]), None);
+        assert_eq!(scope(&[]), None);
+    }
+
+    #[test]
+    fn test_load_project_config_from_repo_root() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            ProjectConfig::load(dir.path()).unwrap().commit,
+            CommitDefaults::default()
+        );
+
+        fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[commit]\nrequire_scope = true\n",
+        )
+        .unwrap();
+        let project = ProjectConfig::load(dir.path()).unwrap();
+        assert_eq!(project.commit.require_scope, Some(true));
+
+        fs::write(dir.path().join(PROJECT_CONFIG_FILE), "[commit\n").unwrap();
+        assert!(ProjectConfig::load(dir.path()).is_err());
+
+        fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[commit]\nsubject_template = \"{type}: {summary}\"\n",
+        )
+        .unwrap();
+        let error = ProjectConfig::load(dir.path()).unwrap_err().to_string();
+        assert!(error.contains("Unknown placeholder {summary}"));
+    }
+
+    #[test]
+    fn test_pr_template_is_injected_into_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load_pr_template(dir.path()).unwrap(), None);
+
+        fs::create_dir(dir.path().join(".github")).unwrap();
+        fs::write(
+            dir.path().join(".github/PULL_REQUEST_TEMPLATE.md"),
+            "## Summary\n\n## Testing\n",
+        )
+        .unwrap();
+        let template = load_pr_template(dir.path()).unwrap().unwrap();
+        assert_eq!(template, "## Summary\n\n## Testing");
+
+        let system =
+            crate::ai::build_system_prompt(&[&crate::ai::pr_template_instruction(&template)]);
+        assert!(system.contains("<pr_template>\n## Summary\n\n## Testing\n</pr_template>"));
+    }
+
+    #[test]
+    fn test_load_prompt_preamble() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            ProjectConfig::load(dir.path()).unwrap().prompt_preamble,
+            None
+        );
+
+        fs::create_dir(dir.path().join(".convmit")).unwrap();
+        fs::write(dir.path().join(PROMPT_PREAMBLE_FILE), "  \n").unwrap();
+        assert_eq!(
+            ProjectConfig::load(dir.path()).unwrap().prompt_preamble,
+            None
+        );
+
+        fs::write(
+            dir.path().join(PROMPT_PREAMBLE_FILE),
+            "# Glossary\n\n- ledger: the billing event store\n",
+        )
+        .unwrap();
+        assert_eq!(
+            ProjectConfig::load(dir.path()).unwrap().prompt_preamble,
+            Some("# Glossary\n\n- ledger: the billing event store".to_string())
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Generated,
+    Skipped,
+    Error,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Report {
+    pub outcome: Outcome,
+    pub message: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Report {
+    // `None` means the run finished without generating anything, e.g. nothing was staged
+    pub fn from_result(result: Result<Option<String>, String>) -> Self {
+        match result {
+            Ok(Some(message)) => Self {
+                outcome: Outcome::Generated,
+                message: Some(message),
+                error: None,
+            },
+            Ok(None) => Self {
+                outcome: Outcome::Skipped,
+                message: None,
+                error: None,
+            },
+            Err(error) => Self {
+                outcome: Outcome::Error,
+                message: None,
+                error: Some(error),
+            },
+        }
+    }
+
+    pub fn exit_code(&self, keep_going: bool) -> i32 {
+        if self.outcome == Outcome::Error && !keep_going {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_report_with_keep_going() {
+        let report = Report::from_result(Err("No API key found for model sonnet4".to_string()));
+
+        assert_eq!(
+            report.to_json().unwrap(),
+            r#"{"outcome":"error","message":null,"error":"No API key found for model sonnet4"}"#
+        );
+        assert_eq!(report.exit_code(true), 0);
+        assert_eq!(report.exit_code(false), 1);
+    }
+
+    #[test]
+    fn test_generated_and_skipped_reports() {
+        let report = Report::from_result(Ok(Some("feat: add thing".to_string())));
+        assert_eq!(
+            report.to_json().unwrap(),
+            r#"{"outcome":"generated","message":"feat: add thing","error":null}"#
+        );
+        assert_eq!(report.exit_code(false), 0);
+
+        let report = Report::from_result(Ok(None));
+        assert_eq!(report.outcome, Outcome::Skipped);
+        assert_eq!(report.exit_code(false), 0);
+    }
+}
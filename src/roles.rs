@@ -0,0 +1,145 @@
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileRole {
+    Docs,
+    Ci,
+    Test,
+    Build,
+    Source,
+}
+
+impl FileRole {
+    fn commit_type(&self) -> Option<&'static str> {
+        match self {
+            FileRole::Docs => Some("docs"),
+            FileRole::Ci => Some("ci"),
+            FileRole::Test => Some("test"),
+            FileRole::Build => Some("build"),
+            FileRole::Source => None,
+        }
+    }
+}
+
+pub fn role_for_path(path: &str) -> FileRole {
+    let lower = path.to_lowercase();
+    let file_name = Path::new(&lower)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_string();
+
+    if lower.starts_with(".github/workflows/")
+        || lower.starts_with(".gitlab-ci")
+        || lower.starts_with(".circleci/")
+        || lower.starts_with(".buildkite/")
+        || file_name == "jenkinsfile"
+    {
+        return FileRole::Ci;
+    }
+
+    if lower.starts_with("docs/")
+        || lower.starts_with("doc/")
+        || [".md", ".markdown", ".rst", ".adoc"]
+            .iter()
+            .any(|extension| file_name.ends_with(extension))
+    {
+        return FileRole::Docs;
+    }
+
+    if lower.starts_with("tests/")
+        || lower.contains("/tests/")
+        || lower.contains("/__tests__/")
+        || file_name.starts_with("test_")
+        || file_name.ends_with("_test.go")
+        || file_name.ends_with("_test.rs")
+        || [".test.", ".spec."]
+            .iter()
+            .any(|marker| file_name.contains(marker))
+    {
+        return FileRole::Test;
+    }
+
+    if matches!(
+        file_name.as_str(),
+        "cargo.toml"
+            | "cargo.lock"
+            | "package.json"
+            | "package-lock.json"
+            | "pnpm-lock.yaml"
+            | "yarn.lock"
+            | "go.mod"
+            | "go.sum"
+            | "build.rs"
+            | "makefile"
+            | "dockerfile"
+    ) {
+        return FileRole::Build;
+    }
+
+    FileRole::Source
+}
+
+// Only confident when every file has the same role, mixed sets are left to the model
+pub fn infer_type(files: &[String]) -> Option<String> {
+    let mut roles = files.iter().map(|file| role_for_path(file));
+    let first = roles.next()?;
+
+    if roles.all(|role| role == first) {
+        first
+            .commit_type()
+            .map(|commit_type| commit_type.to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn test_role_for_path() {
+        assert_eq!(role_for_path("README.md"), FileRole::Docs);
+        assert_eq!(role_for_path("docs/setup.txt"), FileRole::Docs);
+        assert_eq!(role_for_path(".github/workflows/ci.yml"), FileRole::Ci);
+        assert_eq!(role_for_path("tests/integration_tests.rs"), FileRole::Test);
+        assert_eq!(role_for_path("web/src/app.spec.ts"), FileRole::Test);
+        assert_eq!(role_for_path("Cargo.lock"), FileRole::Build);
+        assert_eq!(role_for_path("src/main.rs"), FileRole::Source);
+    }
+
+    #[test]
+    fn test_infer_type_for_single_role_sets() {
+        assert_eq!(
+            infer_type(&files(&["README.md", "docs/guide.md"])),
+            Some("docs".to_string())
+        );
+        assert_eq!(
+            infer_type(&files(&[
+                ".github/workflows/ci.yml",
+                ".github/workflows/release.yml"
+            ])),
+            Some("ci".to_string())
+        );
+        assert_eq!(
+            infer_type(&files(&["tests/integration_tests.rs"])),
+            Some("test".to_string())
+        );
+        assert_eq!(
+            infer_type(&files(&["Cargo.toml", "Cargo.lock"])),
+            Some("build".to_string())
+        );
+    }
+
+    #[test]
+    fn test_infer_type_leaves_ambiguous_sets_to_the_model() {
+        assert_eq!(infer_type(&files(&["src/main.rs", "README.md"])), None);
+        assert_eq!(infer_type(&files(&["src/ai.rs"])), None);
+        assert_eq!(infer_type(&[]), None);
+    }
+}
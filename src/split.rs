@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, RandomState};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitGroup {
+    pub files: Vec<String>,
+    pub message: String,
+}
+
+// Files at the repository root share a group, everything else is grouped by top-level directory
+pub fn group_by_directory(files: &[String]) -> Vec<Vec<String>> {
+    let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for file in files {
+        let key = match file.split_once('/') {
+            Some((directory, _)) => directory,
+            None => "",
+        };
+        groups.entry(key).or_default().push(file.clone());
+    }
+    groups.into_values().collect()
+}
+
+// Random per run, so a message can't guess it and end the heredoc early
+pub fn message_delimiter() -> String {
+    format!(
+        "CONVMIT_MESSAGE_{:016x}",
+        RandomState::new().hash_one(std::process::id())
+    )
+}
+
+// Each commit is limited to its group's paths, so files in other groups stay staged
+pub fn render_commit_script(groups: &[CommitGroup], delimiter: &str) -> anyhow::Result<String> {
+    let mut script = String::from("#!/bin/sh\nset -e\n");
+
+    for group in groups {
+        if group.message.contains(delimiter) {
+            return Err(anyhow::anyhow!(
+                "The message for {} contains the heredoc delimiter {delimiter}",
+                group.files.join(", ")
+            ));
+        }
+        let files: Vec<String> = group.files.iter().map(|file| shell_quote(file)).collect();
+        script.push_str(&format!(
+            "\ngit commit -F - -- {} <<'{delimiter}'\n{}\n{delimiter}\n",
+            files.join(" "),
+            group.message.trim_end()
+        ));
+    }
+
+    Ok(script)
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_by_directory() {
+        let files: Vec<String> = ["src/ai.rs", "README.md", "docs/usage.md", "src/main.rs"]
+            .iter()
+            .map(|file| file.to_string())
+            .collect();
+
+        assert_eq!(
+            group_by_directory(&files),
+            vec![
+                vec!["README.md".to_string()],
+                vec!["docs/usage.md".to_string()],
+                vec!["src/ai.rs".to_string(), "src/main.rs".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_commit_script() {
+        let groups = vec![
+            CommitGroup {
+                files: vec!["src/ai.rs".to_string(), "src/main.rs".to_string()],
+                message: "feat(ai): add provider\n\nWires it into create_client.\n".to_string(),
+            },
+            CommitGroup {
+                files: vec!["docs/it's here.md".to_string()],
+                message: "docs: describe provider".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            render_commit_script(&groups, "CONVMIT_MESSAGE").unwrap(),
+            "#!/bin/sh
+set -e
+
+git commit -F - -- 'src/ai.rs' 'src/main.rs' <<'CONVMIT_MESSAGE'
+feat(ai): add provider
+
+Wires it into create_client.
+CONVMIT_MESSAGE
+
+git commit -F - -- 'docs/it'\\''s here.md' <<'CONVMIT_MESSAGE'
+docs: describe provider
+CONVMIT_MESSAGE
+"
+        );
+    }
+
+    #[test]
+    fn test_render_commit_script_rejects_the_delimiter_in_a_message() {
+        let delimiter = message_delimiter();
+        assert_ne!(delimiter, "CONVMIT_MESSAGE");
+
+        let groups = vec![CommitGroup {
+            files: vec!["src/ai.rs".to_string()],
+            message: format!("feat: add provider\n\n{delimiter}\nrm -rf ~"),
+        }];
+        let error = render_commit_script(&groups, &delimiter).unwrap_err();
+        assert!(error.to_string().contains("src/ai.rs"));
+    }
+}
@@ -0,0 +1,212 @@
+use serde::Deserialize;
+
+use crate::commit::ConventionalCommit;
+
+pub const PLACEHOLDERS: [&str; 4] = ["type", "scope", "breaking", "description"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct SubjectTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+impl SubjectTemplate {
+    pub fn parse(template: &str) -> anyhow::Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or_else(|| {
+                anyhow::anyhow!("Unclosed placeholder in subject template \"{template}\"")
+            })? + start;
+
+            let name = rest[start + 1..end].trim();
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(anyhow::anyhow!(
+                    "Unknown placeholder {{{}}} in subject template, expected one of: {}",
+                    name,
+                    PLACEHOLDERS.join(", ")
+                ));
+            }
+            parts.push(Part::Placeholder(name.to_string()));
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        if !parts
+            .iter()
+            .any(|part| *part == Part::Placeholder("description".to_string()))
+        {
+            return Err(anyhow::anyhow!(
+                "Subject template \"{template}\" must include {{description}}"
+            ));
+        }
+
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, commit: &ConventionalCommit) -> String {
+        let mut pieces: Vec<String> = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(text) => text.as_str(),
+                Part::Placeholder(name) => match name.as_str() {
+                    "type" => commit.commit_type.as_str(),
+                    "scope" => commit.scope.as_deref().unwrap_or_default(),
+                    "breaking" if commit.breaking => "!",
+                    "description" => commit.description.as_str(),
+                    _ => "",
+                },
+            })
+            .map(str::to_string)
+            .collect();
+
+        if commit.scope.as_deref().unwrap_or_default().is_empty() {
+            for (index, part) in self.parts.iter().enumerate() {
+                if *part == Part::Placeholder("scope".to_string()) {
+                    self.drop_scope_brackets(&mut pieces, index);
+                }
+            }
+        }
+
+        pieces.concat().trim().to_string()
+    }
+
+    // Templates like "{type}({scope})" would leave empty brackets behind for unscoped
+    // commits, so the pair around an empty scope is dropped along with the space after it
+    fn drop_scope_brackets(&self, pieces: &mut [String], index: usize) {
+        if index == 0
+            || !matches!(self.parts[index - 1], Part::Literal(_))
+            || !matches!(self.parts.get(index + 1), Some(Part::Literal(_)))
+        {
+            return;
+        }
+
+        let (head, tail) = pieces.split_at_mut(index);
+        let before = &mut head[index - 1];
+        let after = &mut tail[1];
+        for (open, close) in [('(', ')'), ('[', ']')] {
+            if before.ends_with(open) && after.starts_with(close) {
+                before.pop();
+                after.remove(0);
+
+                let preceding = head.concat();
+                if preceding.is_empty() || preceding.ends_with(char::is_whitespace) {
+                    *after = after.trim_start().to_string();
+                }
+                return;
+            }
+        }
+    }
+
+    pub fn apply(&self, message: &str) -> anyhow::Result<String> {
+        let commit = ConventionalCommit::parse(message)?;
+        let subject = self.render(&commit);
+
+        Ok(match message.trim().split_once('\n') {
+            Some((_, rest)) => format!("{subject}\n{rest}"),
+            None => subject,
+        })
+    }
+}
+
+impl TryFrom<String> for SubjectTemplate {
+    type Error = anyhow::Error;
+
+    fn try_from(template: String) -> anyhow::Result<Self> {
+        Self::parse(&template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, message: &str) -> String {
+        SubjectTemplate::parse(template)
+            .unwrap()
+            .render(&ConventionalCommit::parse(message).unwrap())
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let message = "feat(api)!: add pagination";
+
+        assert_eq!(
+            render("{type}({scope}){breaking}: {description}", message),
+            "feat(api)!: add pagination"
+        );
+        assert_eq!(
+            render("[{scope}] {type}: {description}", message),
+            "[api] feat: add pagination"
+        );
+        assert_eq!(
+            render("{type}: {description} (JIRA-123)", message),
+            "feat: add pagination (JIRA-123)"
+        );
+        assert_eq!(
+            render("{type}({scope}): {description}", "fix: handle empty input"),
+            "fix: handle empty input"
+        );
+        assert_eq!(
+            render("[{scope}] {description}", "docs: update readme"),
+            "update readme"
+        );
+    }
+
+    #[test]
+    fn test_render_leaves_description_untouched() {
+        assert_eq!(
+            render(
+                "{type}({scope}): {description}",
+                "fix: call parse() before  validate[]"
+            ),
+            "fix: call parse() before  validate[]"
+        );
+        assert_eq!(
+            render("[{scope}] {description}", "fix(cli): call parse() first"),
+            "[cli] call parse() first"
+        );
+        assert_eq!(
+            render("{type}({scope}) {description}", "fix: call parse() first"),
+            "fix call parse() first"
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        let error = SubjectTemplate::parse("{type}({module}): {description}")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Unknown placeholder {module}"));
+
+        assert!(SubjectTemplate::parse("{type}: {description").is_err());
+        assert!(SubjectTemplate::parse("{type}({scope})").is_err());
+    }
+
+    #[test]
+    fn test_apply_keeps_body_and_footers() {
+        let template = SubjectTemplate::parse("{type}: {description} [{scope}]").unwrap();
+
+        let message = template
+            .apply("fix(parser): handle empty input\n\nReturn early.\n\nRefs: #12")
+            .unwrap();
+
+        assert_eq!(
+            message,
+            "fix: handle empty input [parser]\n\nReturn early.\n\nRefs: #12"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use crate::ai;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Release builds can embed the commit with CONVMIT_GIT_SHA=$(git rev-parse --short HEAD)
+const GIT_SHA: Option<&str> = option_env!("CONVMIT_GIT_SHA");
+
+pub fn version_report(verbose: bool, config_path: &Path) -> String {
+    let mut report = format!("convmit {VERSION}");
+    if !verbose {
+        return report;
+    }
+
+    report.push_str(&format!("\ncommit: {}", GIT_SHA.unwrap_or("unknown")));
+    report.push_str(&format!("\nconfig: {}", config_path.display()));
+    report.push_str(&format!(
+        "\nproviders: {}",
+        ai::compiled_providers().join(", ")
+    ));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbose_version_report() {
+        let path = Path::new("/home/user/.config/convmit/config.toml");
+
+        let report = version_report(true, path);
+
+        assert!(report.starts_with(&format!("convmit {VERSION}")));
+        assert!(report.contains("config: /home/user/.config/convmit/config.toml"));
+        assert!(report.contains(&format!(
+            "providers: {}",
+            ai::compiled_providers().join(", ")
+        )));
+        assert_eq!(version_report(false, path), format!("convmit {VERSION}"));
+    }
+}